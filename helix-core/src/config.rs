@@ -1,4 +1,4 @@
-use crate::syntax::{Configuration, Loader, LoaderError};
+use crate::syntax::{Configuration, LanguageConfiguration, Loader, LoaderError};
 
 /// Language configuration based on built-in languages.toml.
 pub fn default_lang_config() -> Configuration {
@@ -14,6 +14,7 @@ pub fn default_lang_loader() -> Loader {
 
 #[derive(Debug)]
 pub enum LanguageLoaderError {
+    ConfigError(anyhow::Error),
     DeserializeError(toml::de::Error),
     LoaderError(LoaderError),
 }
@@ -21,6 +22,7 @@ pub enum LanguageLoaderError {
 impl std::fmt::Display for LanguageLoaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::ConfigError(err) => write!(f, "Failed to load language config: {err}"),
             Self::DeserializeError(err) => write!(f, "Failed to parse language config: {err}"),
             Self::LoaderError(err) => write!(f, "Failed to compile language config: {err}"),
         }
@@ -30,16 +32,103 @@ impl std::fmt::Display for LanguageLoaderError {
 impl std::error::Error for LanguageLoaderError {}
 
 /// Language configuration based on user configured languages.toml.
-pub fn user_lang_config() -> Result<Configuration, toml::de::Error> {
-    helix_loader::config::user_lang_config()?.try_into()
+pub fn user_lang_config() -> anyhow::Result<Configuration> {
+    Ok(helix_loader::config::user_lang_config()?.try_into()?)
 }
 
 /// Language configuration loader based on user configured languages.toml.
-pub fn user_lang_loader() -> Result<Loader, LanguageLoaderError> {
-    let config: Configuration = helix_loader::config::user_lang_config()
-        .map_err(LanguageLoaderError::DeserializeError)?
-        .try_into()
-        .map_err(LanguageLoaderError::DeserializeError)?;
+///
+/// Invalid `[[language]]` entries are dropped via [`lenient_lang_config`]
+/// rather than failing the whole load; the returned warnings describe
+/// each dropped entry and should be surfaced to the user.
+pub fn user_lang_loader() -> Result<(Loader, Vec<String>), LanguageLoaderError> {
+    let value =
+        helix_loader::config::user_lang_config().map_err(LanguageLoaderError::ConfigError)?;
+    let (config, warnings) =
+        lenient_lang_config(value).map_err(LanguageLoaderError::DeserializeError)?;
+
+    let loader = Loader::new(config).map_err(LanguageLoaderError::LoaderError)?;
+    Ok((loader, warnings))
+}
+
+/// Deserialize a merged `languages.toml` value, tolerating invalid
+/// `[[language]]` entries.
+///
+/// `deny_unknown_fields` on [`LanguageConfiguration`] makes a single
+/// typo'd key - say `file-type` for `file-types` - fail the strict parse
+/// for the whole file. This path deserializes each `[[language]]` entry
+/// individually instead: entries that fail are dropped and reported as
+/// messages naming the entry and the serde error, while the rest of the
+/// configuration loads normally. Errors outside of the language list
+/// still fail the parse.
+pub fn lenient_lang_config(
+    mut value: toml::Value,
+) -> Result<(Configuration, Vec<String>), toml::de::Error> {
+    let entries = match value
+        .as_table_mut()
+        .and_then(|table| table.remove("language"))
+    {
+        Some(toml::Value::Array(entries)) => {
+            // Parse everything but the language list strictly.
+            if let Some(table) = value.as_table_mut() {
+                table.insert("language".to_string(), toml::Value::Array(Vec::new()));
+            }
+            entries
+        }
+        Some(other) => {
+            // Not an array: restore it and let the strict parse report it.
+            if let Some(table) = value.as_table_mut() {
+                table.insert("language".to_string(), other);
+            }
+            Vec::new()
+        }
+        None => Vec::new(),
+    };
 
-    Loader::new(config).map_err(LanguageLoaderError::LoaderError)
+    let mut config: Configuration = value.try_into()?;
+    let mut errors = Vec::new();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let name = entry
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map(|name| format!("'{name}'"))
+            .unwrap_or_else(|| format!("#{index}"));
+        match entry.try_into::<LanguageConfiguration>() {
+            Ok(language) => config.language.push(language),
+            Err(err) => errors.push(format!("ignoring language entry {name}: {err}")),
+        }
+    }
+    Ok((config, errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lenient_lang_config;
+
+    #[test]
+    fn misspelled_key_is_reported_not_fatal() {
+        let value: toml::Value = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+
+            [[language]]
+            name = "broken"
+            scope = "source.broken"
+            file-type = ["brk"]
+            "#,
+        )
+        .unwrap();
+
+        let (config, errors) = lenient_lang_config(value).unwrap();
+
+        // The valid entry loads; the typo'd one is dropped and reported.
+        assert_eq!(config.language.len(), 1);
+        assert_eq!(config.language[0].language_id, "rust");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("'broken'"), "unexpected: {}", errors[0]);
+        assert!(errors[0].contains("file-type"), "unexpected: {}", errors[0]);
+    }
 }
@@ -926,6 +926,46 @@ pub fn treesitter_indent_for_pos<'a>(
     Some(result)
 }
 
+/// Returns the indentation level (in indent units) that the indent query
+/// computes for `line`.
+///
+/// This is a convenience wrapper around [treesitter_indent_for_pos] for
+/// callers that only need a level rather than an indent string: the query
+/// runs at the line's first non-whitespace character (or the line's last
+/// character - the newline - for blank lines) and the net indent is clamped
+/// at zero. Like the rest of the
+/// tree-sitter indent computation this operates on the root layer's tree,
+/// so lines inside an injection are indented by the outer language's query.
+/// If the query yields no result the line's current indent level is
+/// returned instead.
+pub fn indent_level_at(
+    syntax: &Syntax,
+    text: RopeSlice,
+    line: usize,
+    query: &Query,
+    tab_width: usize,
+    indent_width: usize,
+) -> usize {
+    let line_slice = text.line(line);
+    let pos = text.line_to_char(line)
+        + line_slice
+            .first_non_whitespace_char()
+            .unwrap_or_else(|| line_slice.len_chars().saturating_sub(1));
+    match treesitter_indent_for_pos(
+        query,
+        syntax,
+        tab_width,
+        indent_width,
+        text,
+        line,
+        pos,
+        false,
+    ) {
+        Some(indent) => indent.net_indent().max(0) as usize,
+        None => indent_level_for_line(line_slice, tab_width, indent_width),
+    }
+}
+
 /// Returns the indentation for a new line.
 /// This is done either using treesitter, or if that's not available by copying the indentation from the current line
 #[allow(clippy::too_many_arguments)]
@@ -1068,6 +1108,39 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_indent_level_at() {
+        use crate::syntax::{Configuration, HighlightConfiguration, Loader};
+        use arc_swap::ArcSwap;
+        use std::sync::Arc;
+
+        let source = Rope::from_str("fn main() {\n    if true {\n        let x = 1;\n    }\n}\n");
+
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars().get_language("rust").unwrap();
+        let query = Query::new(&language, "((block) @indent)").unwrap();
+
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        let text = source.slice(..);
+        assert_eq!(indent_level_at(&syntax, text, 0, &query, 4, 4), 0);
+        assert_eq!(indent_level_at(&syntax, text, 1, &query, 4, 4), 1);
+        assert_eq!(indent_level_at(&syntax, text, 2, &query, 4, 4), 2);
+    }
+
     #[test]
     fn test_large_indent_level() {
         let tab_width = 16;
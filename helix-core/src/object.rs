@@ -16,6 +16,38 @@ pub fn expand_selection(syntax: &Syntax, text: RopeSlice, selection: Selection)
     )
 }
 
+pub fn expand_selection_n(
+    syntax: &Syntax,
+    text: RopeSlice,
+    selection: Selection,
+    levels: usize,
+) -> Selection {
+    if levels == 0 {
+        return selection;
+    }
+    select_node_impl(
+        syntax,
+        text,
+        selection,
+        |cursor, byte_range| {
+            // Each level repeats the `expand_selection` walk - climb past
+            // nodes covering the current range exactly onto the first
+            // strictly larger ancestor - so one call with `levels` matches
+            // `levels` separate calls without the intermediate selections.
+            let mut byte_range = byte_range;
+            for _ in 0..levels {
+                while cursor.node().byte_range() == byte_range {
+                    if !cursor.goto_parent() {
+                        return;
+                    }
+                }
+                byte_range = cursor.node().byte_range();
+            }
+        },
+        None,
+    )
+}
+
 pub fn shrink_selection(syntax: &Syntax, text: RopeSlice, selection: Selection) -> Selection {
     select_node_impl(
         syntax,
@@ -33,11 +65,250 @@ pub fn shrink_selection(syntax: &Syntax, text: RopeSlice, selection: Selection)
                     break;
                 }
             }
+            // Wrapper nodes (single-child nodes and injection layer roots)
+            // can cover exactly the current selection; shrinking onto one
+            // would appear to do nothing. Descend past them to the first
+            // strictly smaller node, mirroring the same-range skip in
+            // `expand_selection`.
+            while cursor.node().byte_range() == byte_range {
+                if !cursor.goto_first_child() {
+                    break;
+                }
+            }
+        },
+        None,
+    )
+}
+
+pub fn select_smallest_node(syntax: &Syntax, text: RopeSlice, selection: Selection) -> Selection {
+    select_node_impl(
+        syntax,
+        text,
+        selection,
+        |cursor, _byte_range| {
+            // The cursor is already on the smallest node containing the
+            // range; climb past anonymous tokens so a bare cursor lands on
+            // the enclosing named node rather than a zero-width token.
+            while !cursor.node().is_named() {
+                if !cursor.goto_parent() {
+                    break;
+                }
+            }
         },
         None,
     )
 }
 
+pub fn select_node_lines(syntax: &Syntax, text: RopeSlice, selection: Selection) -> Selection {
+    let cursor = &mut syntax.walk();
+
+    selection.transform(|range| {
+        let from = text.char_to_byte(range.from());
+        let to = text.char_to_byte(range.to());
+
+        cursor.reset_to_byte_range(from, to);
+        let node = cursor.node();
+
+        // Expand the node to whole lines - from the start of its first
+        // line through the end of its last, including the trailing line
+        // ending - so the result is ready for line-wise cut and paste.
+        // Nodes that start or end mid-line grow outwards to the line
+        // boundaries.
+        let start_line = text.byte_to_line(node.start_byte());
+        // `end_byte` is exclusive: a node ending in a line ending must not
+        // drag in the following line.
+        let end_line = text.byte_to_line(node.end_byte().saturating_sub(1).max(node.start_byte()));
+        let from = text.line_to_char(start_line);
+        let to = text.line_to_char((end_line + 1).min(text.len_lines()));
+        Range::new(from, to).with_direction(range.direction())
+    })
+}
+
+pub fn select_comment(syntax: &Syntax, text: RopeSlice, selection: Selection) -> Selection {
+    let cursor = &mut syntax.walk();
+
+    selection.transform(|range| {
+        let from = text.char_to_byte(range.from());
+        let to = text.char_to_byte(range.to());
+
+        cursor.reset_to_byte_range(from, to);
+
+        while !cursor.node().kind().contains("comment") {
+            if !cursor.goto_parent() {
+                // The range is not inside a comment: leave it unchanged.
+                return range;
+            }
+        }
+
+        // Grammars commonly represent line comments as separate adjacent
+        // nodes; extend over the contiguous run of sibling comments.
+        let node = cursor.node();
+        let mut start_node = node;
+        while let Some(prev) = start_node.prev_sibling() {
+            if !prev.kind().contains("comment") {
+                break;
+            }
+            start_node = prev;
+        }
+        let mut end_node = node;
+        while let Some(next) = end_node.next_sibling() {
+            if !next.kind().contains("comment") {
+                break;
+            }
+            end_node = next;
+        }
+
+        let from = text.byte_to_char(start_node.start_byte());
+        let to = text.byte_to_char(end_node.end_byte());
+        Range::new(from, to).with_direction(range.direction())
+    })
+}
+
+/// Whether a node kind represents a block-level prose node.
+fn is_block_kind(kind: &str) -> bool {
+    matches!(
+        kind,
+        "paragraph" | "list_item" | "block_quote" | "fenced_code_block" | "indented_code_block"
+    )
+}
+
+pub fn select_block(syntax: &Syntax, text: RopeSlice, selection: Selection) -> Selection {
+    let cursor = &mut syntax.walk();
+
+    selection.transform(|range| {
+        let from = text.char_to_byte(range.from());
+        let to = text.char_to_byte(range.to());
+
+        cursor.reset_to_byte_range(from, to);
+
+        // Climb to the enclosing block-level node. Prose grammars have
+        // dedicated kinds for these; for code the closest equivalent of a
+        // logical block is the enclosing statement or declaration.
+        loop {
+            let kind = cursor.node().kind();
+            if is_block_kind(kind) || kind.contains("statement") || kind.contains("declaration") {
+                break;
+            }
+            if !cursor.goto_parent() {
+                // No block-level node: leave the range unchanged.
+                return range;
+            }
+        }
+
+        let node = cursor.node();
+        let from = text.byte_to_char(node.start_byte());
+        let to = text.byte_to_char(node.end_byte());
+        Range::new(from, to).with_direction(range.direction())
+    })
+}
+
+/// The byte range of the innermost textobject capture containing `from..to`.
+fn textobject_covering(
+    syntax: &Syntax,
+    text: RopeSlice,
+    capture_names: &[&str],
+    from: usize,
+    to: usize,
+) -> Option<std::ops::Range<usize>> {
+    syntax
+        .textobject_nodes(capture_names, text, None)
+        .map(|node| node.byte_range())
+        .filter(|range| range.start <= from && to <= range.end)
+        .min_by_key(|range| range.end - range.start)
+}
+
+/// The body (`function.inside` capture) of the function spanning `function`.
+fn function_body(
+    syntax: &Syntax,
+    text: RopeSlice,
+    function: &std::ops::Range<usize>,
+) -> Option<std::ops::Range<usize>> {
+    syntax
+        .textobject_nodes(&["function.inside"], text, Some(function.clone()))
+        .map(|node| node.byte_range())
+        .filter(|body| function.start <= body.start && body.end <= function.end)
+        // Nested functions contribute their own bodies; the body of the
+        // function itself is the largest one within it.
+        .max_by_key(|body| body.end - body.start)
+}
+
+pub fn select_function_body(syntax: &Syntax, text: RopeSlice, selection: Selection) -> Selection {
+    selection.transform(|range| {
+        let from = text.char_to_byte(range.from());
+        let to = text.char_to_byte(range.to());
+
+        // Ranges outside of a function, or in functions whose textobject
+        // queries don't capture a body, are left unchanged.
+        let Some(function) = textobject_covering(syntax, text, &["function.around"], from, to)
+        else {
+            return range;
+        };
+        let Some(body) = function_body(syntax, text, &function) else {
+            return range;
+        };
+
+        Range::new(text.byte_to_char(body.start), text.byte_to_char(body.end))
+            .with_direction(range.direction())
+    })
+}
+
+pub fn select_function_signature(
+    syntax: &Syntax,
+    text: RopeSlice,
+    selection: Selection,
+) -> Selection {
+    selection.transform(|range| {
+        let from = text.char_to_byte(range.from());
+        let to = text.char_to_byte(range.to());
+
+        let Some(function) = textobject_covering(syntax, text, &["function.around"], from, to)
+        else {
+            return range;
+        };
+
+        // The signature is everything up to the body; a bodyless function
+        // (e.g. a trait method declaration) is all signature.
+        let end = function_body(syntax, text, &function).map_or(function.end, |body| body.start);
+
+        Range::new(text.byte_to_char(function.start), text.byte_to_char(end))
+            .with_direction(range.direction())
+    })
+}
+
+pub fn select_to_sibling_of_kind(
+    syntax: &Syntax,
+    text: RopeSlice,
+    selection: Selection,
+    kind: &str,
+) -> Selection {
+    let cursor = &mut syntax.walk();
+
+    selection.transform(|range| {
+        let from = text.char_to_byte(range.from());
+        let to = text.char_to_byte(range.to());
+
+        cursor.reset_to_byte_range(from, to);
+
+        // Search forward among the selected node's siblings for the
+        // requested kind, e.g. from a `start_tag` to its `end_tag` or from
+        // an `if` arm to its `else`.
+        let mut node = cursor.node();
+        loop {
+            let Some(next) = node.next_sibling() else {
+                // No matching sibling: leave the range unchanged.
+                return range;
+            };
+            node = next;
+            if node.kind() == kind {
+                break;
+            }
+        }
+
+        let to = text.byte_to_char(node.end_byte());
+        Range::new(range.from(), to).with_direction(range.direction())
+    })
+}
+
 pub fn select_next_sibling(syntax: &Syntax, text: RopeSlice, selection: Selection) -> Selection {
     select_node_impl(
         syntax,
@@ -138,3 +409,214 @@ where
         Range::new(from, to).with_direction(direction.unwrap_or_else(|| range.direction()))
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::{Configuration, HighlightConfiguration, Loader};
+    use crate::Rope;
+    use arc_swap::ArcSwap;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn syntax_for(language_name: &str, source: &Rope) -> Syntax {
+        syntax_with_textobjects(language_name, None, source)
+    }
+
+    fn syntax_with_textobjects(
+        language_name: &str,
+        textobjects: Option<&str>,
+        source: &Rope,
+    ) -> Syntax {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars().get_language(language_name).unwrap();
+        let config = HighlightConfiguration::new(
+            language,
+            language_name.to_string(),
+            "",
+            textobjects,
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
+        Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_select_comment() {
+        let source = Rope::from_str("/// a comment on\n/// multiple lines\nfn main() {}\n");
+        let syntax = syntax_for("rust", &source);
+
+        // A cursor inside the first line selects the whole comment block.
+        let selection = select_comment(&syntax, source.slice(..), Selection::point(5));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (0, 35));
+
+        // Ranges outside of comments are left unchanged.
+        let selection = select_comment(&syntax, source.slice(..), Selection::point(40));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (40, 40));
+    }
+
+    #[test]
+    fn test_select_block_markdown_paragraph() {
+        let source = Rope::from_str("para one line a\nline b\n\npara two\n");
+        let syntax = syntax_for("markdown", &source);
+
+        // A cursor in the second line of the first paragraph selects the
+        // whole paragraph.
+        let selection = select_block(&syntax, source.slice(..), Selection::point(18));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (0, 23));
+    }
+
+    #[test]
+    fn test_select_block_rust_statement() {
+        let source = Rope::from_str("fn main() { foo(); }");
+        let syntax = syntax_for("rust", &source);
+
+        // Rust has no prose blocks; fall back to the enclosing statement.
+        let selection = select_block(&syntax, source.slice(..), Selection::point(14));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (12, 18));
+    }
+
+    #[test]
+    fn test_select_function_body_and_signature() {
+        let source =
+            Rope::from_str("const X: u32 = 1;\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n");
+        let syntax = syntax_with_textobjects(
+            "rust",
+            Some("(function_item body: (_) @function.inside) @function.around"),
+            &source,
+        );
+
+        // A cursor inside the body selects the whole block.
+        let selection = select_function_body(&syntax, source.slice(..), Selection::point(50));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (48, 61));
+
+        // The signature is the function up to the start of the body.
+        let selection = select_function_signature(&syntax, source.slice(..), Selection::point(50));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (18, 48));
+
+        // Ranges outside of a function are left unchanged.
+        let selection = select_function_body(&syntax, source.slice(..), Selection::point(3));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (3, 3));
+    }
+
+    #[test]
+    fn test_select_to_sibling_of_kind() {
+        let source = Rope::from_str("<div>hi</div>\n");
+        let syntax = syntax_for("html", &source);
+
+        // A selected opening tag extends over its closing tag.
+        let selection = Selection::single(0, 5);
+        let selection = select_to_sibling_of_kind(&syntax, source.slice(..), selection, "end_tag");
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (0, 13));
+
+        // A kind with no matching sibling leaves the range unchanged.
+        let selection = Selection::single(0, 5);
+        let selection = select_to_sibling_of_kind(&syntax, source.slice(..), selection, "doctype");
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (0, 5));
+    }
+
+    #[test]
+    fn test_shrink_selection_makes_progress() {
+        let source = Rope::from_str("<p>hi</p>");
+        let syntax = syntax_for("html", &source);
+
+        // Shrinking a selection covering the whole element lands on a
+        // strictly smaller node rather than a same-range wrapper
+        // (the document node covers the element exactly here).
+        let selection = shrink_selection(&syntax, source.slice(..), Selection::single(0, 9));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (0, 3));
+
+        // Further shrinks keep making progress down to a leaf token...
+        let selection = shrink_selection(&syntax, source.slice(..), selection);
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (0, 1));
+
+        // ...where shrinking stays put instead of looping.
+        let selection = shrink_selection(&syntax, source.slice(..), selection);
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (0, 1));
+    }
+
+    #[test]
+    fn test_expand_selection_n() {
+        let source = Rope::from_str("fn main() { let x = 1; }");
+        let syntax = syntax_for("rust", &source);
+        let selection = Selection::point(16);
+
+        // Zero levels is the identity.
+        let unchanged = expand_selection_n(&syntax, source.slice(..), selection.clone(), 0);
+        assert_eq!(unchanged, selection);
+
+        // Two levels in one call match two separate expansions.
+        let stepped = expand_selection(
+            &syntax,
+            source.slice(..),
+            expand_selection(&syntax, source.slice(..), selection.clone()),
+        );
+        let direct = expand_selection_n(&syntax, source.slice(..), selection, 2);
+        assert_eq!(direct, stepped);
+
+        // More levels than ancestors saturate at the root.
+        let range =
+            expand_selection_n(&syntax, source.slice(..), Selection::point(16), 99).primary();
+        assert_eq!((range.from(), range.to()), (0, source.len_chars()));
+    }
+
+    #[test]
+    fn test_select_node_lines() {
+        let source = Rope::from_str("fn main() {\n    let x = 1;\n}\n");
+        let syntax = syntax_for("rust", &source);
+
+        // A cursor on `x` expands its statement's line, trailing newline
+        // included.
+        let selection = select_node_lines(&syntax, source.slice(..), Selection::point(20));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (12, 27));
+
+        // A selected multi-line node starting mid-line grows outwards to
+        // whole lines.
+        let selection = select_node_lines(&syntax, source.slice(..), Selection::single(10, 28));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (0, 29));
+    }
+
+    #[test]
+    fn test_select_smallest_node() {
+        let source = Rope::from_str("fn main() { let foobar = 1; }");
+        let syntax = syntax_for("rust", &source);
+
+        // A cursor inside `foobar` selects the whole identifier.
+        let selection = select_smallest_node(&syntax, source.slice(..), Selection::point(18));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (16, 22));
+
+        // A cursor in the whitespace between tokens expands to the
+        // enclosing named node (the block).
+        let selection = select_smallest_node(&syntax, source.slice(..), Selection::point(11));
+        let range = selection.primary();
+        assert_eq!((range.from(), range.to()), (10, 29));
+    }
+}
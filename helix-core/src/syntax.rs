@@ -1,3 +1,4 @@
+pub mod span;
 mod tree_cursor;
 
 use crate::{
@@ -34,6 +35,10 @@ use serde::{ser::SerializeSeq, Deserialize, Serialize};
 
 use helix_loader::grammar;
 
+pub use span::{
+    flat_span_iter, match_spans, snap_spans_to_graphemes, span_iter, styled_ranges, HighlightSet,
+    Span,
+};
 pub use tree_cursor::TreeCursor;
 
 fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
@@ -90,6 +95,67 @@ pub struct Configuration {
     pub language_support_repo: Vec<grammar::Repository>,
 }
 
+impl Configuration {
+    /// The names of all configured languages, sorted alphabetically.
+    ///
+    /// Iterating `language` directly follows the order of the parsed
+    /// `languages.toml`, which is not stable across merged configurations.
+    /// Consumers that generate output from the configuration (e.g. docgen)
+    /// should use this accessor so the result is deterministic.
+    pub fn language_ids(&self) -> impl Iterator<Item = &str> {
+        let mut ids: Vec<_> = self
+            .language
+            .iter()
+            .map(|language| language.language_id.as_str())
+            .collect();
+        ids.sort_unstable();
+        ids.into_iter()
+    }
+
+    /// The language servers configured for `language`, in configuration
+    /// order.
+    ///
+    /// This is the per-language server list (names plus feature filters);
+    /// each entry's command and args live in
+    /// [`Configuration::language_server`] keyed by the name. Unknown
+    /// languages yield an empty slice.
+    pub fn language_servers_for(&self, language: &str) -> &[LanguageServerFeatures] {
+        self.language
+            .iter()
+            .find(|config| config.language_id == language)
+            .map_or(&[], |config| config.language_servers.as_slice())
+    }
+
+    /// The comment tokens configured for `language`.
+    ///
+    /// Returns `None` for unknown languages and for languages that
+    /// configure neither line nor block comments; otherwise either slice
+    /// may be empty when only the other kind is configured.
+    pub fn comment_tokens_for(&self, language: &str) -> Option<CommentTokens<'_>> {
+        let config = self
+            .language
+            .iter()
+            .find(|config| config.language_id == language)?;
+        if config.comment_tokens.is_none() && config.block_comment_tokens.is_none() {
+            return None;
+        }
+        Some(CommentTokens {
+            line: config.comment_tokens.as_deref().unwrap_or(&[]),
+            block: config.block_comment_tokens.as_deref().unwrap_or(&[]),
+        })
+    }
+}
+
+/// A language's comment tokens, borrowed from its
+/// [`LanguageConfiguration`]. See [`Configuration::comment_tokens_for`].
+#[derive(Debug, Clone, Copy)]
+pub struct CommentTokens<'a> {
+    /// Line comment token(s), e.g. `//`; configs may list several.
+    pub line: &'a [String],
+    /// Block comment start/end pairs, e.g. `/*` and `*/`.
+    pub block: &'a [BlockCommentToken],
+}
+
 // largely based on tree-sitter/cli/src/loader.rs
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
@@ -174,8 +240,28 @@ pub struct LanguageConfiguration {
     /// If set, overrides rainbow brackets for a language.
     pub rainbow_brackets: Option<bool>,
 
+    /// Maximum file size in bytes for which tree-sitter highlighting is
+    /// attempted; larger files are shown as plain text. Overrides
+    /// [`DEFAULT_MAX_FILE_SIZE`] for a language, e.g. to lower it for
+    /// languages commonly shipped minified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_file_size: Option<usize>,
+
     #[serde(default)]
     pub persistent_diagnostic_sources: Vec<String>,
+
+    /// Node kinds this language's grammar uses for comments, e.g.
+    /// `line_comment` and `block_comment` for Rust but plain `comment`
+    /// for many other grammars. Consulted by comment-aware scanners such
+    /// as TODO-keyword highlighting; defaults to [`DEFAULT_COMMENT_NODES`]
+    /// when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment_nodes: Option<Vec<String>>,
+
+    /// Node kinds used for strings, analogous to `comment-nodes`.
+    /// Defaults to [`DEFAULT_STRING_NODES`] when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub string_nodes: Option<Vec<String>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
@@ -680,7 +766,56 @@ pub fn read_query(loader: &Loader, language: &str, filename: &str) -> Option<Str
     Some(contents)
 }
 
+/// Node kinds treated as comments for languages that do not declare
+/// `comment-nodes` in `languages.toml`.
+pub const DEFAULT_COMMENT_NODES: &[&str] =
+    &["comment", "line_comment", "block_comment", "doc_comment"];
+
+/// Node kinds treated as strings for languages that do not declare
+/// `string-nodes` in `languages.toml`.
+pub const DEFAULT_STRING_NODES: &[&str] = &[
+    "string",
+    "string_literal",
+    "raw_string_literal",
+    "string_content",
+];
+
 impl LanguageConfiguration {
+    /// The node kinds that count as comments for this language.
+    ///
+    /// Grammars disagree on naming, so comment-aware scanners consult
+    /// this instead of hardcoding kinds.
+    pub fn comment_nodes(&self) -> Vec<&str> {
+        match &self.comment_nodes {
+            Some(nodes) => nodes.iter().map(String::as_str).collect(),
+            None => DEFAULT_COMMENT_NODES.to_vec(),
+        }
+    }
+
+    /// The node kinds that count as strings for this language.
+    pub fn string_nodes(&self) -> Vec<&str> {
+        match &self.string_nodes {
+            Some(nodes) => nodes.iter().map(String::as_str).collect(),
+            None => DEFAULT_STRING_NODES.to_vec(),
+        }
+    }
+
+    /// Whether `kind` names a comment node in this language's grammar.
+    pub fn is_comment_node(&self, kind: &str) -> bool {
+        match &self.comment_nodes {
+            Some(nodes) => nodes.iter().any(|node| node == kind),
+            None => DEFAULT_COMMENT_NODES.contains(&kind),
+        }
+    }
+
+    /// Whether `kind` names a string node in this language's grammar.
+    pub fn is_string_node(&self, kind: &str) -> bool {
+        match &self.string_nodes {
+            Some(nodes) => nodes.iter().any(|node| node == kind),
+            None => DEFAULT_STRING_NODES.contains(&kind),
+        }
+    }
+
     fn initialize_highlight(&self, loader: &Loader) -> Option<Arc<HighlightConfiguration>> {
         let highlights_query = read_query(loader, &self.language_id, "highlights.scm")?;
         // always highlight syntax errors
@@ -706,6 +841,7 @@ impl LanguageConfiguration {
             .ok()?;
         let config = HighlightConfiguration::new(
             language,
+            self.language_id.clone(),
             &highlights_query,
             textobjects_query.as_deref(),
             rainbows_query.as_deref(),
@@ -726,6 +862,12 @@ impl LanguageConfiguration {
         }
     }
 
+    /// The shared highlight configuration for this language.
+    ///
+    /// The configuration is initialized (and `configure`d with the loader's
+    /// scopes) once on first use and every subsequent caller receives a
+    /// clone of the same `Arc`, so all documents of a language share one
+    /// grammar and one set of highlight indices.
     pub fn highlight_config(&self, loader: &Loader) -> Option<Arc<HighlightConfiguration>> {
         self.highlight_config
             .get_or_init(|| self.initialize_highlight(loader))
@@ -957,12 +1099,45 @@ impl Loader {
         best_match_position.map(|i| self.language_configs[i].clone())
     }
 
+    /// Resolve an injection language marker (e.g. the value of an
+    /// `#set! injection.language "rust"` property or a fenced code block
+    /// info string) to a language configuration, by injection regex first,
+    /// then exact language id, then extension file-types. The last
+    /// fallback lets markdown info strings use extension aliases like
+    /// `rs` for Rust.
+    fn language_config_for_injection_marker(
+        &self,
+        marker: &str,
+    ) -> Option<Arc<LanguageConfiguration>> {
+        self.language_config_for_name(marker)
+            .or_else(|| self.language_config_for_language_id(marker))
+            .or_else(|| {
+                self.language_config_ids_by_extension
+                    .get(marker)
+                    .and_then(|&id| self.language_configs.get(id).cloned())
+            })
+    }
+
+    /// The highlight configuration for an injection language marker.
+    ///
+    /// Returns `None` for unknown languages so that callers can treat the
+    /// injected content as plain text.
+    pub fn language_config_for_injection(
+        &self,
+        marker: &str,
+    ) -> Option<Arc<HighlightConfiguration>> {
+        self.language_config_for_injection_marker(marker)?
+            .highlight_config(self)
+    }
+
     pub fn language_configuration_for_injection_string(
         &self,
         capture: &InjectionLanguageMarker,
     ) -> Option<Arc<LanguageConfiguration>> {
         match capture {
-            InjectionLanguageMarker::Name(string) => self.language_config_for_name(string),
+            InjectionLanguageMarker::Name(string) => {
+                self.language_config_for_injection_marker(string)
+            }
             InjectionLanguageMarker::Filename(file) => self.language_config_for_file_name(file),
             InjectionLanguageMarker::Shebang(shebang) => self
                 .language_config_ids_by_shebang
@@ -979,6 +1154,12 @@ impl Loader {
         &self.language_server_configs
     }
 
+    /// Set the active highlight scope names (from the current theme).
+    ///
+    /// Highlight configurations the loader hands out afterwards are
+    /// configured against this list automatically, and already
+    /// initialized ones are reconfigured in place, re-indexing the
+    /// [`Highlight`] values they produce. Call this on theme switch.
     pub fn set_scopes(&self, scopes: Vec<String>) {
         self.scopes.store(Arc::new(scopes));
 
@@ -1000,8 +1181,12 @@ impl Loader {
         &self.grammars
     }
 
+    pub fn grammars_mut(&mut self) -> &mut grammar::Loader {
+        &mut self.grammars
+    }
+
     pub fn read_grammar_file(&self, language: &str, file: &str) -> anyhow::Result<String> {
-        self.grammars.read_grammar_file(language, file)
+        Ok(self.grammars.read_grammar_file(language, file)?)
     }
 }
 
@@ -1051,11 +1236,22 @@ unsafe fn query_captures<'a, 'tree>(
     (cursor, captures)
 }
 
+/// The default budget for a single parse. Half a second is pretty
+/// generous; pathological input against a slow grammar hits it rather
+/// than hanging the calling thread.
+const DEFAULT_PARSE_TIMEOUT_MICROS: u64 = 1000 * 500;
+
+/// The default size in bytes above which [`Syntax::new_with_max_bytes`]
+/// treats a file as plain text. Per-language overridable via the
+/// `max-file-size` key in `languages.toml`.
+pub const DEFAULT_MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+
 #[derive(Debug)]
 pub struct Syntax {
     layers: HopSlotMap<LayerId, LanguageLayer>,
     root: LayerId,
     loader: Arc<ArcSwap<Loader>>,
+    parse_timeout_micros: u64,
 }
 
 fn byte_range_to_str(range: std::ops::Range<usize>, source: RopeSlice) -> Cow<str> {
@@ -1067,6 +1263,45 @@ impl Syntax {
         source: RopeSlice,
         config: Arc<HighlightConfiguration>,
         loader: Arc<ArcSwap<Loader>>,
+    ) -> Option<Self> {
+        Self::new_with_timeout(
+            source,
+            config,
+            loader,
+            std::time::Duration::from_micros(DEFAULT_PARSE_TIMEOUT_MICROS),
+        )
+    }
+
+    /// Like [`Syntax::new`], but refuses sources larger than `max_bytes`.
+    ///
+    /// Returns `None` for oversized sources so the caller falls back to
+    /// plain text instead of stalling on a huge (typically minified or
+    /// generated) file. `max_bytes` usually comes from the language's
+    /// `max-file-size` configuration, defaulting to
+    /// [`DEFAULT_MAX_FILE_SIZE`].
+    pub fn new_with_max_bytes(
+        source: RopeSlice,
+        config: Arc<HighlightConfiguration>,
+        loader: Arc<ArcSwap<Loader>>,
+        max_bytes: usize,
+    ) -> Option<Self> {
+        if source.len_bytes() > max_bytes {
+            return None;
+        }
+        Self::new(source, config, loader)
+    }
+
+    /// Like [`Syntax::new`], but with an explicit parse timeout.
+    ///
+    /// Returns `None` when parsing exceeds the budget, letting the caller
+    /// fall back to plain text instead of blocking on a pathological
+    /// input. The timeout also applies to subsequent [`Syntax::update`]
+    /// calls on the returned instance.
+    pub fn new_with_timeout(
+        source: RopeSlice,
+        config: Arc<HighlightConfiguration>,
+        loader: Arc<ArcSwap<Loader>>,
+        timeout: std::time::Duration,
     ) -> Option<Self> {
         let root_layer = LanguageLayer {
             tree: None,
@@ -1091,6 +1326,7 @@ impl Syntax {
             root,
             layers,
             loader,
+            parse_timeout_micros: timeout.as_micros().try_into().unwrap_or(u64::MAX),
         };
 
         let res = syntax.update(source, source, &ChangeSet::new(source));
@@ -1227,7 +1463,9 @@ impl Syntax {
 
         PARSER.with(|ts_parser| {
             let ts_parser = &mut ts_parser.borrow_mut();
-            ts_parser.parser.set_timeout_micros(1000 * 500); // half a second is pretty generours
+            ts_parser
+                .parser
+                .set_timeout_micros(self.parse_timeout_micros);
             let mut cursor = ts_parser.cursors.pop().unwrap_or_default();
             // TODO: might need to set cursor range
             cursor.set_byte_range(0..usize::MAX);
@@ -1384,6 +1622,42 @@ impl Syntax {
         })
     }
 
+    /// Like [`Syntax::update`], additionally reporting which byte ranges
+    /// of the root tree actually changed structure.
+    ///
+    /// The ranges come from tree-sitter's `Tree::changed_ranges` between
+    /// the edited old tree and the re-parsed one, so a renderer can limit
+    /// re-highlighting to those regions instead of the whole viewport. If
+    /// the root layer had not been parsed before, the whole document is
+    /// reported as changed.
+    pub fn update_with_changed_ranges(
+        &mut self,
+        old_source: RopeSlice,
+        source: RopeSlice,
+        changeset: &ChangeSet,
+    ) -> Result<Vec<std::ops::Range<usize>>, Error> {
+        // `changed_ranges` must compare the re-parsed tree against the old
+        // tree *with the edits applied*, mirroring what `update` does to
+        // the layer's own tree before re-parsing.
+        let mut old_tree = self.layers[self.root].tree.clone();
+        if let Some(tree) = &mut old_tree {
+            for edit in generate_edits(old_source, changeset).iter().rev() {
+                tree.edit(edit);
+            }
+        }
+
+        self.update(old_source, source, changeset)?;
+
+        let ranges = match &old_tree {
+            Some(old_tree) => old_tree
+                .changed_ranges(self.tree())
+                .map(|range| range.start_byte..range.end_byte)
+                .collect(),
+            None => vec![0..source.len_bytes()],
+        };
+        Ok(ranges)
+    }
+
     pub fn tree(&self) -> &Tree {
         self.layers[self.root].tree()
     }
@@ -1436,7 +1710,15 @@ impl Syntax {
             .layers
             .iter()
             .filter_map(|(_, layer)| {
-                // TODO: if range doesn't overlap layer range, skip it
+                // Skip layers that don't overlap the query range: their
+                // captures cannot produce any events within it.
+                if let Some(range) = &range {
+                    if !layer.ranges.iter().any(|layer_range| {
+                        layer_range.start_byte < range.end && layer_range.end_byte > range.start
+                    }) {
+                        return None;
+                    }
+                }
 
                 let (cursor, captures) = unsafe {
                     query_captures(
@@ -1473,7 +1755,8 @@ impl Syntax {
 
         HighlightIter {
             source,
-            byte_offset: range.map_or(0, |r| r.start),
+            byte_offset: range.as_ref().map_or(0, |r| r.start),
+            end_byte: range.map_or(usize::MAX, |r| r.end),
             cancellation_flag,
             iter_count: 0,
             layers,
@@ -1623,6 +1906,47 @@ impl Syntax {
         spans
     }
 
+    /// Runs an arbitrary `query` over the viewport and converts its captures
+    /// into [`Span`]s.
+    ///
+    /// `capture_to_scope` maps capture names (without the leading `@`) to
+    /// highlight scopes; captures the map doesn't mention are skipped. This
+    /// generalizes query-driven overlay features — rainbow-style or semantic
+    /// highlighting — through one mechanism instead of hardcoding node
+    /// kinds per feature. The query must be compiled for the root layer's
+    /// grammar, so injected layers are not searched. The result is sorted
+    /// for [`span_iter`].
+    pub fn query_spans(
+        &self,
+        source: RopeSlice,
+        query: &Query,
+        capture_to_scope: &HashMap<String, usize>,
+        view: std::ops::Range<usize>,
+    ) -> Vec<Span> {
+        let capture_names = query.capture_names();
+        // SAFETY: `captures` is dropped at the end of this function, before
+        // `_cursor`.
+        let (_cursor, captures) = unsafe {
+            query_captures(
+                query,
+                self.layers[self.root].tree().root_node(),
+                source,
+                Some(view),
+            )
+        };
+
+        let mut spans: Vec<_> = captures
+            .filter_map(|(match_, capture_index)| {
+                let capture = match_.captures[capture_index];
+                let scope = *capture_to_scope.get(capture_names[capture.index as usize])?;
+                let range = capture.node.byte_range();
+                Some(Span::new(scope, range.start, range.end))
+            })
+            .collect();
+        spans.sort_unstable();
+        spans
+    }
+
     pub fn tree_for_byte_range(&self, start: usize, end: usize) -> &Tree {
         let mut container_id = self.root;
 
@@ -1655,6 +1979,56 @@ impl Syntax {
         TreeCursor::new(&self.layers, self.root)
     }
 
+    /// The name of the language the root layer is parsed with.
+    ///
+    /// Injected layers report their own languages via [`Self::injection_layers`].
+    pub fn root_language(&self) -> &str {
+        self.layers[self.root].config.language_name()
+    }
+
+    /// Iterate over the injected layers, yielding each injected byte range
+    /// together with the name of the language injected there.
+    ///
+    /// The root layer is not reported. Combined injections yield one entry
+    /// per range. This is read-only introspection for debugging and UIs
+    /// like "show injected regions".
+    pub fn injection_layers(&self) -> impl Iterator<Item = (std::ops::Range<usize>, &str)> {
+        self.layers
+            .iter()
+            .filter(|(_, layer)| layer.parent.is_some())
+            .flat_map(|(_, layer)| {
+                layer.ranges.iter().map(|range| {
+                    (
+                        range.start_byte..range.end_byte,
+                        layer.config.language_name(),
+                    )
+                })
+            })
+    }
+
+    /// The innermost injection containing `byte`, as its injected byte
+    /// range and language name.
+    ///
+    /// Returns `None` when `byte` lies only in the base layer. Among
+    /// nested injections the deepest layer wins, the narrower range
+    /// breaking ties, mirroring the layer selection in `tree_cursor.rs`.
+    /// This backs "act on this injected block" features such as
+    /// evaluating or formatting a single code fence.
+    pub fn injection_at_byte(&self, byte: usize) -> Option<(std::ops::Range<usize>, &str)> {
+        self.layers
+            .values()
+            .filter(|layer| layer.parent.is_some())
+            .flat_map(|layer| {
+                layer
+                    .ranges
+                    .iter()
+                    .map(move |range| (range.start_byte..range.end_byte, layer))
+            })
+            .filter(|(range, _)| range.contains(&byte))
+            .max_by_key(|(range, layer)| (layer.depth, std::cmp::Reverse(range.end - range.start)))
+            .map(|(range, layer)| (range, layer.config.language_name()))
+    }
+
     // Commenting
     // comment_strings_for_pos
     // is_commented
@@ -1664,7 +2038,133 @@ impl Syntax {
     // suggested_indent_for_buffer_row
     // indent_level_for_line
 
-    // TODO: Folding
+    // Folding
+
+    /// Computes the foldable byte ranges of the document.
+    ///
+    /// Multi-line block-like nodes (blocks, bodies, arrays) are fold
+    /// candidates, including those inside injection layers. By convention
+    /// the first line of a fold stays visible, so each range starts at the
+    /// end of the node's opening line rather than at the node itself. The
+    /// result is sorted and de-duplicated.
+    pub fn fold_ranges(&self, text: RopeSlice) -> Vec<std::ops::Range<usize>> {
+        fn is_fold_kind(kind: &str) -> bool {
+            kind == "block"
+                || kind.ends_with("_block")
+                || kind.ends_with("body")
+                || matches!(kind, "array" | "array_expression" | "object" | "table")
+        }
+
+        let mut ranges = Vec::new();
+        let mut cursor = self.walk();
+
+        'dfs: loop {
+            let node = cursor.node();
+            let start = node.start_byte().min(text.len_bytes());
+            let end = node.end_byte().min(text.len_bytes());
+            let start_line = text.byte_to_line(start);
+            if is_fold_kind(node.kind()) && start_line < text.byte_to_line(end) {
+                let fold_start = crate::line_ending::line_end_byte_index(&text, start_line);
+                if fold_start < end {
+                    ranges.push(fold_start..end);
+                }
+            }
+
+            if cursor.goto_first_child() {
+                continue;
+            }
+            loop {
+                if cursor.goto_next_sibling() {
+                    continue 'dfs;
+                }
+                if !cursor.goto_parent() {
+                    break 'dfs;
+                }
+            }
+        }
+
+        ranges.sort_unstable_by_key(|range| (range.start, range.end));
+        ranges.dedup();
+        ranges
+    }
+
+    // Debugging
+
+    /// Pretty-print the syntax tree for interactive debugging.
+    ///
+    /// One line per named node, indented by depth, prefixed with the
+    /// node's field name in its parent (when it has one) and optionally
+    /// suffixed with its `start..end` byte range. Nodes covered exactly
+    /// by an injection layer print the injected tree beneath them.
+    /// Anonymous nodes are hidden; [`pretty_print_tree`] prints the full
+    /// s-expression form of a single tree instead.
+    pub fn pretty_print<W: fmt::Write>(&self, out: &mut W, include_ranges: bool) -> fmt::Result {
+        self.pretty_print_layer(out, self.root, 0, include_ranges)
+    }
+
+    fn pretty_print_layer<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        layer_id: LayerId,
+        depth: usize,
+        include_ranges: bool,
+    ) -> fmt::Result {
+        let root = self.layers[layer_id].tree().root_node();
+        let mut cursor = root.walk();
+        self.pretty_print_node(out, &mut cursor, layer_id, depth, include_ranges)
+    }
+
+    fn pretty_print_node<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        cursor: &mut tree_sitter::TreeCursor,
+        layer_id: LayerId,
+        depth: usize,
+        include_ranges: bool,
+    ) -> fmt::Result {
+        let node = cursor.node();
+        let visible = node_is_visible(&node);
+
+        let child_depth = if visible {
+            let indentation_columns = depth * 2;
+            write!(out, "{:indentation_columns$}", "")?;
+            if let Some(field_name) = cursor.field_name() {
+                write!(out, "{}: ", field_name)?;
+            }
+            write!(out, "{}", node.kind())?;
+            if include_ranges {
+                write!(out, " {}..{}", node.start_byte(), node.end_byte())?;
+            }
+            out.write_char('\n')?;
+            depth + 1
+        } else {
+            depth
+        };
+
+        // An injection layer covering this node exactly replaces the
+        // node's children, mirroring `TreeCursor::goto_first_child`.
+        if let Some(injected) = self.layers.iter().find_map(|(id, layer)| {
+            (layer.parent == Some(layer_id)
+                && layer.ranges.iter().any(|range| {
+                    range.start_byte == node.start_byte() && range.end_byte == node.end_byte()
+                }))
+            .then_some(id)
+        }) {
+            return self.pretty_print_layer(out, injected, child_depth, include_ranges);
+        }
+
+        if cursor.goto_first_child() {
+            loop {
+                self.pretty_print_node(out, cursor, layer_id, child_depth, include_ranges)?;
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+            cursor.goto_parent();
+        }
+
+        Ok(())
+    }
 }
 
 /// Finds the child of `node` which contains the given byte range `range`.
@@ -1730,6 +2230,11 @@ impl LanguageLayer {
         self.tree.as_ref().unwrap()
     }
 
+    /// The name of the language this layer is parsed with.
+    pub fn language_name(&self) -> &str {
+        self.config.language_name()
+    }
+
     fn parse(&mut self, parser: &mut Parser, source: RopeSlice) -> Result<(), Error> {
         parser
             .set_included_ranges(&self.ranges)
@@ -1786,6 +2291,16 @@ impl LanguageLayer {
     }
 }
 
+/// The tree-sitter [`Point`] (zero-based row and column) for `byte`.
+///
+/// tree-sitter expects *byte* columns, so the column after a multi-byte
+/// character advances by its UTF-8 length, not by one.
+pub(crate) fn byte_to_point(text: RopeSlice, byte: usize) -> Point {
+    let line = text.byte_to_line(byte);
+    let line_start_byte = text.line_to_byte(line);
+    Point::new(line, byte - line_start_byte)
+}
+
 pub(crate) fn generate_edits(
     old_text: RopeSlice,
     changeset: &ChangeSet,
@@ -1805,11 +2320,7 @@ pub(crate) fn generate_edits(
 
     fn point_at_pos(text: RopeSlice, pos: usize) -> (usize, Point) {
         let byte = text.char_to_byte(pos); // <- attempted to index past end
-        let line = text.char_to_line(pos);
-        let line_start_byte = text.line_to_byte(line);
-        let col = byte - line_start_byte;
-
-        (byte, Point::new(line, col))
+        (byte, byte_to_point(text, byte))
     }
 
     fn traverse(point: Point, text: &Tendril) -> Point {
@@ -1904,6 +2415,30 @@ const CANCELLATION_CHECK_INTERVAL: usize = 100;
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Highlight(pub usize);
 
+impl Highlight {
+    /// The largest scope a [`HighlightSet`] can track: the set records
+    /// scopes as bits of a per-byte `u128`.
+    pub const MAX_SCOPE: usize = 127;
+
+    /// Builds a highlight, returning `None` for scopes above
+    /// [`Self::MAX_SCOPE`].
+    ///
+    /// Use this when converting untrusted span sources so that the limit
+    /// surfaces at construction rather than wrapping silently inside
+    /// [`HighlightSet`].
+    pub fn new(scope: usize) -> Option<Self> {
+        (scope <= Self::MAX_SCOPE).then_some(Self(scope))
+    }
+
+    /// Builds a highlight without bounds-checking the scope.
+    ///
+    /// For trusted producers whose scopes index a theme-derived table and
+    /// are in range by construction, such as [`span_iter`].
+    pub fn new_unchecked(scope: usize) -> Self {
+        Self(scope)
+    }
+}
+
 /// Represents the reason why syntax highlighting failed.
 #[derive(Debug, PartialEq, Eq)]
 pub enum Error {
@@ -1914,7 +2449,7 @@ pub enum Error {
 }
 
 /// Represents a single step in rendering a syntax-highlighted document.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum HighlightEvent {
     Source { start: usize, end: usize },
     HighlightStart(Highlight),
@@ -1927,6 +2462,7 @@ pub enum HighlightEvent {
 #[derive(Debug)]
 pub struct HighlightConfiguration {
     pub language: Grammar,
+    language_name: String,
     query: Query,
     textobjects_query: Option<Query>,
     rainbow_query: Option<Query>,
@@ -1935,6 +2471,7 @@ pub struct HighlightConfiguration {
     combined_injections_patterns: Vec<usize>,
     highlights_pattern_index: usize,
     highlight_indices: ArcSwap<Vec<Option<Highlight>>>,
+    recognized_names: ArcSwap<Vec<String>>,
     non_local_variable_patterns: Vec<bool>,
     injection_content_capture_index: Option<u32>,
     injection_language_capture_index: Option<u32>,
@@ -1966,6 +2503,8 @@ struct LocalScope<'a> {
 struct HighlightIter<'a> {
     source: RopeSlice<'a>,
     byte_offset: usize,
+    /// The end of the query range: no `Source` event extends past this.
+    end_byte: usize,
     cancellation_flag: Option<&'a AtomicUsize>,
     layers: Vec<HighlightIterLayer<'a>>,
     iter_count: usize,
@@ -2029,6 +2568,7 @@ impl HighlightConfiguration {
     /// Returns a `HighlightConfiguration` that can then be used with the `highlight` method.
     pub fn new(
         language: Grammar,
+        language_name: String,
         highlights_query: &str,
         textobjects_query: Option<&str>,
         rainbow_query: Option<&str>,
@@ -2130,6 +2670,7 @@ impl HighlightConfiguration {
         let highlight_indices = ArcSwap::from_pointee(vec![None; query.capture_names().len()]);
         Ok(Self {
             language,
+            language_name,
             query,
             textobjects_query,
             rainbow_query,
@@ -2138,6 +2679,7 @@ impl HighlightConfiguration {
             combined_injections_patterns,
             highlights_pattern_index,
             highlight_indices,
+            recognized_names: ArcSwap::from_pointee(Vec::new()),
             non_local_variable_patterns,
             injection_content_capture_index,
             injection_language_capture_index,
@@ -2152,11 +2694,52 @@ impl HighlightConfiguration {
         })
     }
 
+    /// Like [`HighlightConfiguration::new`], but with the highlights query
+    /// given as fragments concatenated in inheritance order.
+    ///
+    /// Languages that inherit highlights from another language (TSX from
+    /// TypeScript from JavaScript) compile one combined query; taking the
+    /// fragments base-first keeps the capture indices that
+    /// [`configure`](HighlightConfiguration::configure) assigns consistent
+    /// with the inheritance chain while sparing callers the string
+    /// concatenation.
+    pub fn new_with_fragments(
+        language: Grammar,
+        language_name: String,
+        highlights_query_fragments: &[&str],
+        textobjects_query: Option<&str>,
+        rainbow_query: Option<&str>,
+        symbols_query: Option<&str>,
+        injection_query: &str,
+        locals_query: &str,
+    ) -> Result<Self, QueryError> {
+        let mut highlights_query = String::new();
+        for fragment in highlights_query_fragments {
+            highlights_query.push_str(fragment);
+            highlights_query.push('\n');
+        }
+        Self::new(
+            language,
+            language_name,
+            &highlights_query,
+            textobjects_query,
+            rainbow_query,
+            symbols_query,
+            injection_query,
+            locals_query,
+        )
+    }
+
     /// Get a slice containing all of the highlight names used in the configuration.
     pub fn names(&self) -> &[&str] {
         self.query.capture_names()
     }
 
+    /// The name of the language this configuration highlights.
+    pub fn language_name(&self) -> &str {
+        &self.language_name
+    }
+
     /// Set the list of recognized highlight names.
     ///
     /// Tree-sitter syntax-highlighting queries specify highlights in the form of dot-separated
@@ -2201,6 +2784,18 @@ impl HighlightConfiguration {
             .collect();
 
         self.highlight_indices.store(Arc::new(indices));
+        self.recognized_names
+            .store(Arc::new(recognized_names.to_vec()));
+    }
+
+    /// Look up the recognized name a `Highlight` refers to.
+    ///
+    /// `Highlight` values are indices into the name list passed to
+    /// [`HighlightConfiguration::configure`], which makes raw highlight
+    /// events opaque when debugging. Returns `None` for out-of-bounds
+    /// indices or if `configure` has not been called yet.
+    pub fn scope_name(&self, highlight: Highlight) -> Option<String> {
+        self.recognized_names.load().get(highlight.0).cloned()
     }
 
     fn injection_pair<'a>(
@@ -2523,6 +3118,9 @@ impl<'a> HighlightIter<'a> {
         offset: usize,
         event: Option<HighlightEvent>,
     ) -> Option<Result<HighlightEvent, Error>> {
+        // Captures may extend beyond the query range; clamp so that no
+        // source text outside the range is emitted.
+        let offset = offset.min(self.end_byte);
         let result;
         if self.byte_offset < offset {
             result = Some(Ok(HighlightEvent::Source {
@@ -2563,7 +3161,7 @@ impl<'a> Iterator for HighlightIter<'a> {
 
             // If none of the layers have any more highlight boundaries, terminate.
             if self.layers.is_empty() {
-                let len = self.source.len_bytes();
+                let len = self.source.len_bytes().min(self.end_byte);
                 return if self.byte_offset < len {
                     let result = Some(Ok(HighlightEvent::Source {
                         start: self.byte_offset,
@@ -2773,6 +3371,11 @@ pub struct Merge<I> {
     next_span: Option<(usize, std::ops::Range<usize>)>,
 
     queue: Vec<HighlightEvent>,
+
+    // Only populated through `merge_reporting`; plain `merge` leaves the
+    // flag off so dropped spans cost nothing to skip.
+    report_dropped: bool,
+    dropped_spans: Vec<(usize, std::ops::Range<usize>)>,
 }
 
 /// Merge a list of spans into the highlight event stream.
@@ -2787,18 +3390,123 @@ pub fn merge<I: Iterator<Item = HighlightEvent>>(
         next_event: None,
         next_span: None,
         queue: Vec::new(),
+        report_dropped: false,
+        dropped_spans: Vec::new(),
     };
     merge.next_event = merge.iter.next();
     merge.next_span = merge.spans.next();
     merge
 }
 
-impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
+/// Like [`merge`], but over a boxed base stream.
+///
+/// The editor composes base streams of differing concrete types — the
+/// syntax highlighter, [`span_iter`], [`flat_span_iter`] — which forces
+/// boxing at the call site anyway; this spells out the trait-object type
+/// once instead of at every caller. Semantics are identical to `merge`.
+pub fn merge_boxed(
+    iter: Box<dyn Iterator<Item = HighlightEvent>>,
+    spans: Vec<(usize, std::ops::Range<usize>)>,
+) -> Merge<Box<dyn Iterator<Item = HighlightEvent>>> {
+    merge(iter, spans)
+}
+
+/// Merge freshly built selection spans over a cached syntax event stream.
+///
+/// Selection highlights change on every cursor move while the underlying
+/// syntax events usually don't, so callers can collect the highlighter's
+/// events once and re-run only the merge per selection change. The cached
+/// events are borrowed and copied through the returned iterator; the
+/// result is identical to rebuilding the full merged stream.
+pub fn merge_cached_syntax(
+    syntax_events: &[HighlightEvent],
+    selection_spans: Vec<Span>,
+) -> impl Iterator<Item = HighlightEvent> + '_ {
+    let spans = selection_spans
+        .into_iter()
+        .map(|span| (span.scope, span.start..span.end))
+        .collect();
+    merge(syntax_events.iter().copied(), spans)
+}
+
+/// Which input stream of [`merge`] a [`HighlightEvent`] originated from.
+///
+/// `Left` is the base event iterator, `Right` the overlaid span list.
+/// `Source` events are attributed to whichever stream is currently active:
+/// source text emitted inside an overlaid span is tagged `Right`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HighlightSource {
+    Left,
+    Right,
+}
+
+/// Like [`merge`], but tags every event with the stream it originated
+/// from. The event ordering is exactly that of `merge`; only the tags are
+/// added. Useful for debugging overlapping highlights.
+pub fn merge_tagged<I: Iterator<Item = HighlightEvent>>(
+    iter: I,
+    spans: Vec<(usize, std::ops::Range<usize>)>,
+) -> MergeTagged<I> {
+    MergeTagged(merge(iter, spans))
+}
+
+pub struct MergeTagged<I>(Merge<I>);
+
+/// Like [`merge`], but reports span-list highlights that never make it
+/// into the merged stream.
+///
+/// A span whose range ends before the base stream's next `Source` event
+/// begins — typically a diagnostic lying above the highlighted viewport —
+/// is silently discarded by `merge`. This variant appends each such span
+/// to `dropped` as a `HighlightStart`/`Source`/`HighlightEnd` triple so
+/// the caller can render a gutter marker or similar in its place. Spans
+/// that are merely clipped to the viewport are not reported. The merged
+/// event stream itself is identical to `merge`'s.
+pub fn merge_reporting<I: Iterator<Item = HighlightEvent>>(
+    iter: I,
+    spans: Vec<(usize, std::ops::Range<usize>)>,
+    dropped: &mut Vec<HighlightEvent>,
+) -> MergeReporting<'_, I> {
+    let mut merge = merge(iter, spans);
+    merge.report_dropped = true;
+    MergeReporting { merge, dropped }
+}
+
+pub struct MergeReporting<'a, I> {
+    merge: Merge<I>,
+    dropped: &'a mut Vec<HighlightEvent>,
+}
+
+impl<I: Iterator<Item = HighlightEvent>> Iterator for MergeReporting<'_, I> {
     type Item = HighlightEvent;
     fn next(&mut self) -> Option<Self::Item> {
+        let event = self.merge.next();
+        for (span, range) in self.merge.dropped_spans.drain(..) {
+            self.dropped
+                .push(HighlightEvent::HighlightStart(Highlight(span)));
+            self.dropped.push(HighlightEvent::Source {
+                start: range.start,
+                end: range.end,
+            });
+            self.dropped.push(HighlightEvent::HighlightEnd);
+        }
+        event
+    }
+}
+
+impl<I: Iterator<Item = HighlightEvent>> Iterator for MergeTagged<I> {
+    type Item = (HighlightSource, HighlightEvent);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_tagged()
+    }
+}
+
+impl<I: Iterator<Item = HighlightEvent>> Merge<I> {
+    fn next_tagged(&mut self) -> Option<(HighlightSource, HighlightEvent)> {
         use HighlightEvent::*;
         if let Some(event) = self.queue.pop() {
-            return Some(event);
+            // Queued events are always generated from the span list.
+            return Some((HighlightSource::Right, event));
         }
 
         loop {
@@ -2806,6 +3514,9 @@ impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
                 // this happens when range is partially or fully offscreen
                 (Some(Source { start, .. }), Some((span, range))) if start > range.start => {
                     if start > range.end {
+                        if self.report_dropped {
+                            self.dropped_spans.push((*span, range.clone()));
+                        }
                         self.next_span = self.spans.next();
                     } else {
                         self.next_span = Some((*span, start..range.end));
@@ -2818,11 +3529,11 @@ impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
         match (self.next_event, &self.next_span) {
             (Some(HighlightStart(i)), _) => {
                 self.next_event = self.iter.next();
-                Some(HighlightStart(i))
+                Some((HighlightSource::Left, HighlightStart(i)))
             }
             (Some(HighlightEnd), _) => {
                 self.next_event = self.iter.next();
-                Some(HighlightEnd)
+                Some((HighlightSource::Left, HighlightEnd))
             }
             (Some(Source { start, end }), Some((_, range))) if start < range.start => {
                 let intersect = range.start.min(end);
@@ -2842,7 +3553,7 @@ impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
                     });
                 };
 
-                Some(event)
+                Some((HighlightSource::Left, event))
             }
             (Some(Source { start, end }), Some((span, range))) if start == range.start => {
                 let intersect = range.end.min(end);
@@ -2872,11 +3583,11 @@ impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
                     self.next_span = Some((*span, intersect..range.end));
                 }
 
-                Some(event)
+                Some((HighlightSource::Right, event))
             }
             (Some(event), None) => {
                 self.next_event = self.iter.next();
-                Some(event)
+                Some((HighlightSource::Left, event))
             }
             // Can happen if cursor at EOF and/or diagnostic reaches past the end.
             // We need to actually emit events for the cursor-at-EOF situation,
@@ -2891,7 +3602,7 @@ impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
                     end: range.end,
                 });
                 self.next_span = self.spans.next();
-                Some(event)
+                Some((HighlightSource::Right, event))
             }
             (None, None) => None,
             e => unreachable!("{:?}", e),
@@ -2899,27 +3610,250 @@ impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
     }
 }
 
-fn node_is_visible(node: &Node) -> bool {
-    node.is_missing() || (node.is_named() && node.language().node_kind_is_visible(node.kind_id()))
+impl<I: Iterator<Item = HighlightEvent>> Iterator for Merge<I> {
+    type Item = HighlightEvent;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_tagged().map(|(_, event)| event)
+    }
 }
 
-pub fn pretty_print_tree<W: fmt::Write>(fmt: &mut W, node: Node) -> fmt::Result {
-    if node.child_count() == 0 {
-        if node_is_visible(&node) {
-            write!(fmt, "({})", node.kind())
-        } else {
-            write!(fmt, "\"{}\"", node.kind())
-        }
-    } else {
-        pretty_print_tree_impl(fmt, &mut node.walk(), 0)
+/// Drop redundant zero-width highlights from an event stream.
+///
+/// [`merge`] and `span_iter` can emit a `HighlightStart(scope)` followed
+/// immediately by `HighlightEnd` (a zero-width highlight) which is then
+/// re-opened with the very same scope. Renderers see this as a pointless
+/// style flip, so this adapter removes the zero-width pair and keeps the
+/// re-opened highlight. Pairs separated by a `Source` event are left
+/// alone, and the output satisfies the same invariants as the input.
+pub fn coalesce_events<I: Iterator<Item = HighlightEvent>>(
+    events: I,
+) -> impl Iterator<Item = HighlightEvent> {
+    use std::collections::VecDeque;
+
+    struct Coalesce<I> {
+        iter: I,
+        buffer: VecDeque<HighlightEvent>,
     }
-}
 
-fn pretty_print_tree_impl<W: fmt::Write>(
-    fmt: &mut W,
-    cursor: &mut tree_sitter::TreeCursor,
-    depth: usize,
-) -> fmt::Result {
+    impl<I: Iterator<Item = HighlightEvent>> Iterator for Coalesce<I> {
+        type Item = HighlightEvent;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            use HighlightEvent::*;
+
+            loop {
+                while self.buffer.len() < 3 {
+                    match self.iter.next() {
+                        Some(event) => self.buffer.push_back(event),
+                        None => break,
+                    }
+                }
+                match (self.buffer.front(), self.buffer.get(1), self.buffer.get(2)) {
+                    (
+                        Some(&HighlightStart(open)),
+                        Some(HighlightEnd),
+                        Some(&HighlightStart(next)),
+                    ) if open == next => {
+                        // Drop the zero-width pair; the same scope opens
+                        // again immediately.
+                        self.buffer.pop_front();
+                        self.buffer.pop_front();
+                    }
+                    _ => return self.buffer.pop_front(),
+                }
+            }
+        }
+    }
+
+    Coalesce {
+        iter: events,
+        buffer: VecDeque::new(),
+    }
+}
+
+/// Rewrite every highlight in an event stream to its canonical scope.
+///
+/// `mapping[i]` gives the canonical [`Highlight`] for scope index `i`;
+/// scopes past the end of `mapping` are left unchanged. Themes often
+/// style several capture scopes identically, so after remapping a
+/// highlight may open directly inside another with the same canonical
+/// scope - such redundant nesting is collapsed into the outer highlight.
+/// The output satisfies the same invariants as the input.
+pub fn remap_highlights<'a, I: Iterator<Item = HighlightEvent> + 'a>(
+    events: I,
+    mapping: &'a [Highlight],
+) -> impl Iterator<Item = HighlightEvent> + 'a {
+    struct Remap<'a, I> {
+        iter: I,
+        mapping: &'a [Highlight],
+        // Open highlights, paired with whether their start was emitted
+        // (false when collapsed into an identical enclosing highlight).
+        stack: Vec<(Highlight, bool)>,
+    }
+
+    impl<I: Iterator<Item = HighlightEvent>> Iterator for Remap<'_, I> {
+        type Item = HighlightEvent;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            use HighlightEvent::*;
+
+            loop {
+                match self.iter.next()? {
+                    HighlightStart(highlight) => {
+                        let canonical = self.mapping.get(highlight.0).copied().unwrap_or(highlight);
+                        let redundant = self
+                            .stack
+                            .last()
+                            .is_some_and(|&(open, _)| open == canonical);
+                        self.stack.push((canonical, !redundant));
+                        if !redundant {
+                            return Some(HighlightStart(canonical));
+                        }
+                    }
+                    HighlightEnd => {
+                        let (_, emitted) = self
+                            .stack
+                            .pop()
+                            .expect("HighlightEnd events are balanced with starts");
+                        if emitted {
+                            return Some(HighlightEnd);
+                        }
+                    }
+                    source => return Some(source),
+                }
+            }
+        }
+    }
+
+    Remap {
+        iter: events,
+        mapping,
+        stack: Vec::new(),
+    }
+}
+
+/// Which end of the highlight stack [`cap_stack_depth`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthCap {
+    /// Keep the first `max` highlights opened (the enclosing ones).
+    Outermost,
+    /// Keep the last `max` highlights opened (the ones on top).
+    Innermost,
+}
+
+/// Limit an event stream to at most `max` nested highlights.
+///
+/// Deeply stacked overlaps (e.g. many diagnostics on one range) cost
+/// renderers time for levels that never become visible. This adapter
+/// tracks the full stack but only emits the `max` highlights selected by
+/// `keep` around each `Source` event, closing and reopening highlights as
+/// the visible window over the stack shifts. Starts and ends stay
+/// balanced; zero-width highlights (with no `Source` inside) are dropped.
+pub fn cap_stack_depth<I: Iterator<Item = HighlightEvent>>(
+    events: I,
+    max: usize,
+    keep: DepthCap,
+) -> impl Iterator<Item = HighlightEvent> {
+    struct CapDepth<I> {
+        iter: I,
+        max: usize,
+        keep: DepthCap,
+        // The logical stack of the input stream.
+        stack: Vec<Highlight>,
+        // The highlights currently open in the output.
+        emitted: Vec<Highlight>,
+        queue: VecDeque<HighlightEvent>,
+        done: bool,
+    }
+
+    impl<I: Iterator<Item = HighlightEvent>> Iterator for CapDepth<I> {
+        type Item = HighlightEvent;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            use HighlightEvent::*;
+
+            loop {
+                if let Some(event) = self.queue.pop_front() {
+                    return Some(event);
+                }
+                if self.done {
+                    return None;
+                }
+                match self.iter.next() {
+                    None => {
+                        self.done = true;
+                        for _ in self.emitted.drain(..) {
+                            self.queue.push_back(HighlightEnd);
+                        }
+                    }
+                    Some(HighlightStart(highlight)) => self.stack.push(highlight),
+                    Some(HighlightEnd) => {
+                        self.stack.pop();
+                    }
+                    Some(source @ Source { .. }) => {
+                        // Re-sync the emitted highlights with the capped
+                        // view of the stack before any source text.
+                        let desired: Vec<Highlight> = match self.keep {
+                            DepthCap::Outermost => {
+                                self.stack[..self.max.min(self.stack.len())].to_vec()
+                            }
+                            DepthCap::Innermost => {
+                                self.stack[self.stack.len().saturating_sub(self.max)..].to_vec()
+                            }
+                        };
+                        let common = self
+                            .emitted
+                            .iter()
+                            .zip(&desired)
+                            .take_while(|(a, b)| a == b)
+                            .count();
+                        for _ in common..self.emitted.len() {
+                            self.queue.push_back(HighlightEnd);
+                        }
+                        self.emitted.truncate(common);
+                        for &highlight in &desired[common..] {
+                            self.emitted.push(highlight);
+                            self.queue.push_back(HighlightStart(highlight));
+                        }
+                        self.queue.push_back(source);
+                    }
+                }
+            }
+        }
+    }
+
+    CapDepth {
+        iter: events,
+        max,
+        keep,
+        stack: Vec::new(),
+        emitted: Vec::new(),
+        queue: VecDeque::new(),
+        done: false,
+    }
+}
+
+fn node_is_visible(node: &Node) -> bool {
+    node.is_missing() || (node.is_named() && node.language().node_kind_is_visible(node.kind_id()))
+}
+
+pub fn pretty_print_tree<W: fmt::Write>(fmt: &mut W, node: Node) -> fmt::Result {
+    if node.child_count() == 0 {
+        if node_is_visible(&node) {
+            write!(fmt, "({})", node.kind())
+        } else {
+            write!(fmt, "\"{}\"", node.kind())
+        }
+    } else {
+        pretty_print_tree_impl(fmt, &mut node.walk(), 0)
+    }
+}
+
+fn pretty_print_tree_impl<W: fmt::Write>(
+    fmt: &mut W,
+    cursor: &mut tree_sitter::TreeCursor,
+    depth: usize,
+) -> fmt::Result {
     let node = cursor.node();
     let visible = node_is_visible(&node);
 
@@ -3026,8 +3960,17 @@ mod test {
         .unwrap();
         let language = loader.grammars.get_language("rust").unwrap();
 
-        let config =
-            HighlightConfiguration::new(language, "", Some(query_str), None, None, "", "").unwrap();
+        let config = HighlightConfiguration::new(
+            language,
+            "rust".to_string(),
+            "",
+            Some(query_str),
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
         let syntax = Syntax::new(
             source.slice(..),
             Arc::new(config),
@@ -3056,6 +3999,132 @@ mod test {
         // test("multiple_nodes_grouped", 1..37);
     }
 
+    #[test]
+    fn test_language_ids_sorted() {
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "zig"
+            scope = "source.zig"
+            file-types = ["zig"]
+
+            [[language]]
+            name = "agda"
+            scope = "source.agda"
+            file-types = ["agda"]
+
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            "#,
+        )
+        .unwrap();
+
+        let ids: Vec<_> = config.language_ids().collect();
+        assert_eq!(ids, &["agda", "rust", "zig"]);
+    }
+
+    #[test]
+    fn test_language_config_for_injection() {
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            injection-regex = "rust?"
+            file-types = ["rs"]
+
+            [[language]]
+            name = "ocaml"
+            scope = "source.ocaml"
+            file-types = ["ml"]
+            "#,
+        )
+        .unwrap();
+        let loader = Loader::new(config).unwrap();
+
+        // Resolved through the injection regex.
+        let rust = loader.language_config_for_injection_marker("rust").unwrap();
+        assert_eq!(rust.language_id, "rust");
+
+        // Without an injection regex the exact language id still resolves.
+        let ocaml = loader
+            .language_config_for_injection_marker("ocaml")
+            .unwrap();
+        assert_eq!(ocaml.language_id, "ocaml");
+
+        // Extension file-types act as aliases, so a fenced code block like
+        // ```rs resolves to Rust as well.
+        let rs = loader.language_config_for_injection_marker("rs").unwrap();
+        assert_eq!(rs.language_id, "rust");
+
+        // Unknown languages fail gracefully so the injection is treated as
+        // plain text.
+        assert!(loader
+            .language_config_for_injection_marker("nonsense")
+            .is_none());
+        assert!(loader.language_config_for_injection("nonsense").is_none());
+    }
+
+    #[test]
+    fn test_comment_and_string_nodes() {
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            comment-nodes = ["line_comment", "block_comment"]
+            string-nodes = ["string_literal", "raw_string_literal"]
+
+            [[language]]
+            name = "ocaml"
+            scope = "source.ocaml"
+            file-types = ["ml"]
+            "#,
+        )
+        .unwrap();
+        let loader = Loader::new(config).unwrap();
+
+        // Declared kinds are returned verbatim and filter exactly.
+        let rust = loader.language_config_for_name("rust").unwrap();
+        assert_eq!(rust.comment_nodes(), vec!["line_comment", "block_comment"]);
+        assert!(rust.is_comment_node("block_comment"));
+        assert!(!rust.is_comment_node("comment"));
+        assert!(rust.is_string_node("raw_string_literal"));
+        assert!(!rust.is_string_node("string"));
+
+        // Languages without a declaration fall back to the default sets.
+        let ocaml = loader.language_config_for_name("ocaml").unwrap();
+        assert_eq!(ocaml.comment_nodes(), DEFAULT_COMMENT_NODES.to_vec());
+        assert!(ocaml.is_comment_node("comment"));
+        assert!(ocaml.is_string_node("string_literal"));
+        assert!(!ocaml.is_string_node("identifier"));
+    }
+
+    #[test]
+    fn test_highlight_config_is_shared() {
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            "#,
+        )
+        .unwrap();
+        let loader = Loader::new(config).unwrap();
+
+        let language_config = loader.language_config_for_name("rust").unwrap();
+        let first = language_config.highlight_config(&loader).unwrap();
+        let second = language_config.highlight_config(&loader).unwrap();
+
+        // Every document of a language shares the same configuration, so
+        // `configure` only runs once per language.
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
     #[test]
     fn test_parser() {
         let highlight_names: Vec<String> = [
@@ -3093,6 +4162,7 @@ mod test {
         let language = loader.grammars.get_language("rust").unwrap();
         let config = HighlightConfiguration::new(
             language,
+            "rust".to_string(),
             &std::fs::read_to_string("../runtime/grammars/sources/rust/queries/highlights.scm")
                 .unwrap(),
             None, // textobjects.scm
@@ -3135,79 +4205,1091 @@ mod test {
     }
 
     #[test]
-    fn test_input_edits() {
-        use tree_sitter::InputEdit;
+    fn test_scope_name() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
 
-        let doc = Rope::from("hello world!\ntest 123");
-        let transaction = Transaction::change(
-            &doc,
-            vec![(6, 11, Some("test".into())), (12, 17, None)].into_iter(),
-        );
-        let edits = generate_edits(doc.slice(..), transaction.changes());
-        // transaction.apply(&mut state);
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config = HighlightConfiguration::new(
+            language,
+            "rust".to_string(),
+            r#""fn" @keyword"#,
+            None,
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
+
+        // Unconfigured highlights have no names to resolve against.
+        assert_eq!(config.scope_name(Highlight(0)), None);
+
+        let names: Vec<String> = ["function", "keyword"]
+            .iter()
+            .copied()
+            .map(String::from)
+            .collect();
+        config.configure(&names);
+        assert_eq!(config.scope_name(Highlight(0)).as_deref(), Some("function"));
+        assert_eq!(config.scope_name(Highlight(1)).as_deref(), Some("keyword"));
+        assert_eq!(config.scope_name(Highlight(2)), None);
+    }
+
+    #[test]
+    fn test_configure_highlight_fallback() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config = HighlightConfiguration::new(
+            language,
+            "rust".to_string(),
+            "(function_item) @function.builtin",
+            None,
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
 
+        // An unmatched dotted capture falls back to the longest recognized
+        // prefix: `function.builtin` degrades to `function` when the theme
+        // only configures the latter.
+        config.configure(&["function".to_string()]);
         assert_eq!(
-            edits,
-            &[
-                InputEdit {
-                    start_byte: 6,
-                    old_end_byte: 11,
-                    new_end_byte: 10,
-                    start_position: Point { row: 0, column: 6 },
-                    old_end_position: Point { row: 0, column: 11 },
-                    new_end_position: Point { row: 0, column: 10 }
-                },
-                InputEdit {
-                    start_byte: 12,
-                    old_end_byte: 17,
-                    new_end_byte: 12,
-                    start_position: Point { row: 0, column: 12 },
-                    old_end_position: Point { row: 1, column: 4 },
-                    new_end_position: Point { row: 0, column: 12 }
-                }
-            ]
+            config.highlight_indices.load().as_slice(),
+            &[Some(Highlight(0))]
         );
 
-        // Testing with the official example from tree-sitter
-        let mut doc = Rope::from("fn test() {}");
-        let transaction =
-            Transaction::change(&doc, vec![(8, 8, Some("a: u32".into()))].into_iter());
-        let edits = generate_edits(doc.slice(..), transaction.changes());
-        transaction.apply(&mut doc);
-
-        assert_eq!(doc, "fn test(a: u32) {}");
+        // The more specific name wins when both prefixes are recognized.
+        config.configure(&["function".to_string(), "function.builtin".to_string()]);
         assert_eq!(
-            edits,
-            &[InputEdit {
-                start_byte: 8,
-                old_end_byte: 8,
-                new_end_byte: 14,
-                start_position: Point { row: 0, column: 8 },
-                old_end_position: Point { row: 0, column: 8 },
-                new_end_position: Point { row: 0, column: 14 }
-            }]
+            config.highlight_indices.load().as_slice(),
+            &[Some(Highlight(1))]
         );
+
+        // Sibling scopes are not a fallback: `function.method` does not
+        // match `function.builtin`.
+        config.configure(&["function.method".to_string()]);
+        assert_eq!(config.highlight_indices.load().as_slice(), &[None]);
     }
 
-    #[track_caller]
-    fn assert_pretty_print(
-        language_name: &str,
-        source: &str,
-        expected: &str,
-        start: usize,
-        end: usize,
-    ) {
-        let source = Rope::from_str(source);
+    #[test]
+    fn test_highlight_iter_bounded_to_range() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config = HighlightConfiguration::new(
+            language,
+            "rust".to_string(),
+            r#""fn" @keyword"#,
+            None,
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
+        config.configure(&["keyword".to_string()]);
+
+        let source = Rope::from_str("fn a() {}\nfn b() {}\nfn c() {}\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        // Only the `fn b() {}` line.
+        let range = 10..20;
+        let events: Vec<_> = syntax
+            .highlight_iter(source.slice(..), Some(range.clone()), None)
+            .map(|event| event.unwrap())
+            .collect();
 
+        for event in &events {
+            if let HighlightEvent::Source { start, end } = event {
+                assert!(
+                    *start >= range.start && *end <= range.end,
+                    "source {start}..{end} escapes query range {range:?}"
+                );
+            }
+        }
+        // The `fn` keyword on the second line is still highlighted.
+        assert!(events.contains(&HighlightEvent::Source { start: 10, end: 12 }));
+    }
+
+    #[test]
+    fn test_injection_layers() {
         let loader = Loader::new(Configuration {
             language: vec![],
             language_server: HashMap::new(),
             language_support_repo: vec![],
         })
         .unwrap();
-        let language = loader.grammars.get_language(language_name).unwrap();
 
-        let config = HighlightConfiguration::new(language, "", None, None, None, "", "").unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+        assert_eq!(config.language_name(), "rust");
+
+        let source = Rope::from_str("fn main() {}\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        // Without injection queries there is only the root layer, which is
+        // not reported.
+        assert_eq!(syntax.injection_layers().count(), 0);
+    }
+
+    #[test]
+    fn test_reset_to_byte_range_out_of_bounds() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+
+        let source = Rope::from_str("fn main() {}\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        let mut cursor = syntax.walk();
+
+        // A range entirely past EOF is clamped to the end of the tree.
+        cursor.reset_to_byte_range(1000, 1010);
+        assert!(cursor.node().end_byte() <= source.len_bytes());
+
+        // A range crossing EOF resolves to the node covering the in-bounds
+        // part.
+        cursor.reset_to_byte_range(5, 1000);
+        assert_eq!(cursor.node().kind(), "source_file");
+    }
+
+    #[test]
+    fn test_kind_at_byte() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+
+        let source = Rope::from_str("fn main() { let s = \"hello\"; }\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        let mut cursor = syntax.walk();
+
+        // A byte inside the string resolves to the string's node kind.
+        let kind = cursor.kind_at_byte(23).unwrap();
+        assert!(kind.contains("string"), "unexpected kind {kind:?}");
+
+        // Out-of-bounds bytes have no node.
+        assert_eq!(cursor.kind_at_byte(1000), None);
+    }
+
+    #[test]
+    fn test_reset_to_syntax() {
+        let loader = Arc::new(ArcSwap::from_pointee(
+            Loader::new(Configuration {
+                language: vec![],
+                language_server: HashMap::new(),
+                language_support_repo: vec![],
+            })
+            .unwrap(),
+        ));
+        let language = loader.load().grammars.get_language("rust").unwrap();
+
+        let syntax_for = |source: &Rope| {
+            let config = HighlightConfiguration::new(
+                language.clone(),
+                "rust".to_string(),
+                "",
+                None,
+                None,
+                None,
+                "",
+                "",
+            )
+            .unwrap();
+            Syntax::new(source.slice(..), Arc::new(config), loader.clone()).unwrap()
+        };
+
+        let first = Rope::from_str("fn one() {}\n");
+        let second = Rope::from_str("struct Two;\nfn two() {}\n");
+        let first_syntax = syntax_for(&first);
+        let second_syntax = syntax_for(&second);
+
+        let mut cursor = first_syntax.walk();
+        cursor.reset_to_byte_range(3, 6);
+        assert_eq!(cursor.node().kind(), "identifier");
+
+        // Re-pointing the cursor at another tree navigates that tree
+        // correctly.
+        cursor.reset_to_syntax(&second_syntax);
+        assert_eq!(cursor.node().kind(), "source_file");
+        cursor.reset_to_byte_range(0, 11);
+        assert_eq!(cursor.node().kind(), "struct_item");
+    }
+
+    #[test]
+    fn test_goto_point() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+
+        let source = Rope::from_str("fn main() {\n    let s = \"hi\";\n}\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+        let mut cursor = syntax.walk();
+
+        // (row 1, byte column 8) is the `s` binding.
+        cursor.goto_point(source.slice(..), 1, 8);
+        assert_eq!(cursor.node().kind(), "identifier");
+        assert_eq!(cursor.node().byte_range(), 20..21);
+
+        // A column past the end of the line clamps to the line's end.
+        cursor.goto_point(source.slice(..), 0, 99);
+        assert_eq!(cursor.node().kind(), "block");
+
+        // A row past the end of the document clamps too.
+        cursor.goto_point(source.slice(..), 99, 0);
+        assert_eq!(cursor.node().kind(), "source_file");
+    }
+
+    #[test]
+    fn test_injection_at_byte() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("markdown").unwrap();
+        let config = HighlightConfiguration::new(
+            language,
+            "markdown".to_string(),
+            "",
+            None,
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
+
+        let source = Rope::from_str("prose\n\n```rust\nfn main() {}\n```\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        // Without an injections query there are no injection layers; every
+        // byte lies in the base layer only.
+        assert_eq!(syntax.injection_at_byte(2), None);
+        assert_eq!(syntax.injection_at_byte(16), None);
+        assert!(syntax.injection_layers().next().is_none());
+    }
+
+    #[test]
+    fn test_root_language() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("html").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "html".to_string(), "", None, None, None, "", "")
+                .unwrap();
+
+        let source = Rope::from_str("<html><script>var x = 1;</script></html>\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        assert_eq!(syntax.root_language(), "html");
+        // Without an injections query the script element stays in the base
+        // layer; with one, `injection_layers` would report it as javascript.
+        assert!(syntax.injection_layers().next().is_none());
+    }
+
+    #[test]
+    fn test_new_with_max_bytes() {
+        let loader = Arc::new(ArcSwap::from_pointee(
+            Loader::new(Configuration {
+                language: vec![],
+                language_server: HashMap::new(),
+                language_support_repo: vec![],
+            })
+            .unwrap(),
+        ));
+        let language = loader.load().grammars.get_language("rust").unwrap();
+        let config = Arc::new(
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap(),
+        );
+
+        let source = Rope::from_str("fn main() {}\n");
+
+        // A source exceeding the threshold yields no syntax...
+        assert!(
+            Syntax::new_with_max_bytes(source.slice(..), config.clone(), loader.clone(), 4)
+                .is_none()
+        );
+
+        // ...while a smaller one parses as usual.
+        let syntax = Syntax::new_with_max_bytes(source.slice(..), config, loader, 1024).unwrap();
+        assert_eq!(syntax.tree().root_node().kind(), "source_file");
+    }
+
+    #[test]
+    fn test_fold_ranges() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+
+        let source =
+            Rope::from_str("fn outer() {\n    fn inner() {\n        let x = 1;\n    }\n}\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        // The outer and inner function blocks fold; each fold starts at the
+        // end of its opening line so that line stays visible.
+        assert_eq!(syntax.fold_ranges(source.slice(..)), vec![12..56, 29..54]);
+    }
+
+    #[test]
+    fn test_syntax_pretty_print() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+
+        // The small struct/fn source from `test_parser`.
+        let source = Rope::from_str(
+            "
+            struct Stuff {}
+            fn main() {}
+        ",
+        );
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        let mut output = String::new();
+        syntax.pretty_print(&mut output, false).unwrap();
+        assert_eq!(
+            output,
+            "\
+source_file
+  struct_item
+    name: type_identifier
+    body: field_declaration_list
+  function_item
+    name: identifier
+    parameters: parameters
+    body: block
+"
+        );
+
+        let mut output = String::new();
+        syntax.pretty_print(&mut output, true).unwrap();
+        assert!(
+            output.contains("name: type_identifier 20..25"),
+            "unexpected output:\n{output}"
+        );
+    }
+
+    #[test]
+    fn test_query_spans() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config = HighlightConfiguration::new(
+            language.clone(),
+            "rust".to_string(),
+            "",
+            None,
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
+
+        let source = Rope::from_str("fn main() { let s = \"hi\"; }\n");
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        let query = Query::new(
+            &language,
+            r#"["fn" "let"] @keyword (string_literal) @string (identifier) @variable"#,
+        )
+        .unwrap();
+        let capture_to_scope: HashMap<String, usize> =
+            [("keyword".to_string(), 1), ("string".to_string(), 2)]
+                .into_iter()
+                .collect();
+
+        // `@variable` captures have no mapping and are skipped.
+        assert_eq!(
+            syntax.query_spans(
+                source.slice(..),
+                &query,
+                &capture_to_scope,
+                0..source.len_bytes()
+            ),
+            vec![
+                Span::new(1, 0, 2),
+                Span::new(1, 12, 15),
+                Span::new(2, 20, 24)
+            ]
+        );
+
+        // Only captures intersecting the viewport are reported.
+        assert_eq!(
+            syntax.query_spans(source.slice(..), &query, &capture_to_scope, 0..10),
+            vec![Span::new(1, 0, 2)]
+        );
+    }
+
+    #[test]
+    fn test_highlight_new_bounds() {
+        assert_eq!(Highlight::new(0), Some(Highlight(0)));
+        assert_eq!(
+            Highlight::new(Highlight::MAX_SCOPE),
+            Some(Highlight(Highlight::MAX_SCOPE))
+        );
+        // Scopes beyond the `HighlightSet` bitmask are rejected...
+        assert_eq!(Highlight::new(Highlight::MAX_SCOPE + 1), None);
+        // ...while the unchecked constructor takes anything.
+        assert_eq!(Highlight::new_unchecked(200), Highlight(200));
+    }
+
+    #[test]
+    fn test_merge_tagged() {
+        use HighlightEvent::*;
+        use HighlightSource::*;
+
+        let syntax_stream = vec![
+            HighlightStart(Highlight(0)),
+            Source { start: 0, end: 10 },
+            HighlightEnd,
+        ];
+        let overlay = vec![(1, 3..6)];
+
+        let tagged: Vec<_> = merge_tagged(syntax_stream.iter().copied(), overlay.clone()).collect();
+        assert_eq!(
+            tagged,
+            vec![
+                (Left, HighlightStart(Highlight(0))),
+                (Left, Source { start: 0, end: 3 }),
+                (Right, HighlightStart(Highlight(1))),
+                (Right, Source { start: 3, end: 6 }),
+                (Right, HighlightEnd),
+                (Left, Source { start: 6, end: 10 }),
+                (Left, HighlightEnd),
+            ]
+        );
+
+        // Stripping the tags yields exactly the `merge` output.
+        let untagged: Vec<_> = merge(syntax_stream.into_iter(), overlay).collect();
+        assert_eq!(
+            untagged,
+            tagged
+                .into_iter()
+                .map(|(_, event)| event)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_merge_boxed() {
+        use span::{flat_span_iter, span_iter, Span};
+
+        let overlay = vec![(2, 3..6)];
+
+        // The base stream's concrete type is only known at runtime; both
+        // pick the same `merge` semantics through the boxed wrapper.
+        for overlapping in [true, false] {
+            let base: Box<dyn Iterator<Item = HighlightEvent>> = if overlapping {
+                Box::new(span_iter(vec![Span::new(0, 0, 10), Span::new(1, 2, 8)]))
+            } else {
+                Box::new(flat_span_iter(vec![Span::new(0, 0, 10)]))
+            };
+            let base_events: Vec<_> = base.collect();
+
+            let boxed: Vec<_> =
+                merge_boxed(Box::new(base_events.clone().into_iter()), overlay.clone()).collect();
+            let unboxed: Vec<_> = merge(base_events.into_iter(), overlay.clone()).collect();
+            assert_eq!(boxed, unboxed);
+        }
+    }
+
+    #[test]
+    fn test_merge_cached_syntax() {
+        use span::{span_iter, Span};
+
+        // Stands in for the (unchanged) syntax highlighter output.
+        let syntax_events: Vec<_> =
+            span_iter(vec![Span::new(0, 0, 20), Span::new(1, 4, 12)]).collect();
+
+        // After a cursor move only the selection spans are rebuilt.
+        let selection = vec![Span::new(2, 6, 9)];
+        let cached: Vec<_> = merge_cached_syntax(&syntax_events, selection.clone()).collect();
+
+        let full_rebuild: Vec<_> = merge(
+            syntax_events.into_iter(),
+            selection
+                .into_iter()
+                .map(|span| (span.scope, span.start..span.end))
+                .collect(),
+        )
+        .collect();
+        assert_eq!(cached, full_rebuild);
+    }
+
+    #[test]
+    fn test_merge_reporting() {
+        use HighlightEvent::*;
+
+        // The base stream starts at byte 10; the first overlay span ends
+        // before that and is dropped entirely, the second is merged in.
+        let base = vec![
+            HighlightStart(Highlight(0)),
+            Source { start: 10, end: 20 },
+            HighlightEnd,
+        ];
+        let overlay = vec![(1, 0..5), (2, 12..14)];
+
+        let mut dropped = Vec::new();
+        let merged: Vec<_> =
+            merge_reporting(base.clone().into_iter(), overlay.clone(), &mut dropped).collect();
+
+        assert_eq!(
+            dropped,
+            vec![
+                HighlightStart(Highlight(1)),
+                Source { start: 0, end: 5 },
+                HighlightEnd,
+            ]
+        );
+
+        // The merged stream itself is identical to plain `merge`.
+        let plain: Vec<_> = merge(base.into_iter(), overlay).collect();
+        assert_eq!(merged, plain);
+    }
+
+    #[test]
+    fn test_remap_highlights() {
+        use HighlightEvent::*;
+
+        // Scopes 0 and 1 are styled identically by the theme: both map to
+        // scope 0. Scope 2 keeps its own style.
+        let mapping = [Highlight(0), Highlight(0), Highlight(2)];
+
+        let events = vec![
+            HighlightStart(Highlight(0)),
+            HighlightStart(Highlight(1)),
+            Source { start: 0, end: 4 },
+            HighlightStart(Highlight(2)),
+            Source { start: 4, end: 6 },
+            HighlightEnd,
+            HighlightEnd,
+            Source { start: 6, end: 8 },
+            HighlightEnd,
+        ];
+
+        // The nested scope-1 highlight collapses into the enclosing
+        // scope-0 one; the distinct scope-2 highlight survives.
+        let remapped: Vec<_> = remap_highlights(events.into_iter(), &mapping).collect();
+        assert_eq!(
+            remapped,
+            vec![
+                HighlightStart(Highlight(0)),
+                Source { start: 0, end: 4 },
+                HighlightStart(Highlight(2)),
+                Source { start: 4, end: 6 },
+                HighlightEnd,
+                Source { start: 6, end: 8 },
+                HighlightEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cap_stack_depth() {
+        use HighlightEvent::*;
+
+        // Five nested highlights around one source region.
+        let events = vec![
+            HighlightStart(Highlight(0)),
+            HighlightStart(Highlight(1)),
+            HighlightStart(Highlight(2)),
+            HighlightStart(Highlight(3)),
+            HighlightStart(Highlight(4)),
+            Source { start: 4, end: 6 },
+            HighlightEnd,
+            HighlightEnd,
+            HighlightEnd,
+            HighlightEnd,
+            HighlightEnd,
+        ];
+
+        let outermost: Vec<_> =
+            cap_stack_depth(events.clone().into_iter(), 2, DepthCap::Outermost).collect();
+        assert_eq!(
+            outermost,
+            vec![
+                HighlightStart(Highlight(0)),
+                HighlightStart(Highlight(1)),
+                Source { start: 4, end: 6 },
+                HighlightEnd,
+                HighlightEnd,
+            ]
+        );
+
+        let innermost: Vec<_> =
+            cap_stack_depth(events.into_iter(), 2, DepthCap::Innermost).collect();
+        assert_eq!(
+            innermost,
+            vec![
+                HighlightStart(Highlight(3)),
+                HighlightStart(Highlight(4)),
+                Source { start: 4, end: 6 },
+                HighlightEnd,
+                HighlightEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_with_fragments() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+
+        // A base highlights fragment plus an inheriting override fragment
+        // compile into one query with captures from both.
+        let config = HighlightConfiguration::new_with_fragments(
+            language,
+            "rust".to_string(),
+            &[
+                "(function_item name: (identifier) @function)",
+                "\"fn\" @keyword",
+            ],
+            None,
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
+
+        assert!(config.names().contains(&"function"));
+        assert!(config.names().contains(&"keyword"));
+    }
+
+    #[test]
+    fn test_language_servers_for() {
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            language-servers = ["rust-analyzer", "tailwindcss-ls"]
+            "#,
+        )
+        .unwrap();
+
+        let servers = config.language_servers_for("rust");
+        let names: Vec<_> = servers.iter().map(|server| server.name.as_str()).collect();
+        assert_eq!(names, vec!["rust-analyzer", "tailwindcss-ls"]);
+
+        assert!(config.language_servers_for("teal").is_empty());
+    }
+
+    #[test]
+    fn test_comment_tokens_for() {
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            comment-token = "//"
+            block-comment-tokens = { start = "/*", end = "*/" }
+
+            [[language]]
+            name = "text"
+            scope = "source.text"
+            file-types = ["txt"]
+            "#,
+        )
+        .unwrap();
+
+        let tokens = config.comment_tokens_for("rust").unwrap();
+        assert_eq!(tokens.line, ["//"]);
+        assert_eq!(tokens.block.len(), 1);
+        assert_eq!(tokens.block[0].start, "/*");
+        assert_eq!(tokens.block[0].end, "*/");
+
+        // Known language without comment configuration, and an unknown
+        // language, both yield no tokens.
+        assert!(config.comment_tokens_for("text").is_none());
+        assert!(config.comment_tokens_for("teal").is_none());
+    }
+
+    #[test]
+    fn test_set_scopes_reindexes_highlights() {
+        let config: Configuration = toml::from_str(
+            r#"
+            [[language]]
+            name = "rust"
+            scope = "source.rust"
+            file-types = ["rs"]
+            "#,
+        )
+        .unwrap();
+        let loader = Loader::new(config).unwrap();
+
+        loader.set_scopes(vec!["keyword".to_string(), "function".to_string()]);
+        let highlight_config = loader
+            .language_config_for_name("rust")
+            .unwrap()
+            .highlight_config(&loader)
+            .unwrap();
+        assert_eq!(
+            highlight_config.scope_name(Highlight(0)).as_deref(),
+            Some("keyword")
+        );
+
+        // A theme switch reorders the scopes; the shared configuration is
+        // reconfigured in place and Highlight values re-index.
+        loader.set_scopes(vec!["function".to_string(), "keyword".to_string()]);
+        assert_eq!(
+            highlight_config.scope_name(Highlight(0)).as_deref(),
+            Some("function")
+        );
+    }
+
+    #[test]
+    fn test_update_with_changed_ranges() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+
+        let mut source = Rope::from("fn a() {}\nfn b() {}\nfn c() {}\n");
+        let mut syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        // Give `fn b` a body, leaving the other lines untouched.
+        let transaction = Transaction::change(
+            &source,
+            vec![(18, 18, Some(" let x = 1; ".into()))].into_iter(),
+        );
+        let old_source = source.clone();
+        transaction.apply(&mut source);
+
+        let ranges = syntax
+            .update_with_changed_ranges(
+                old_source.slice(..),
+                source.slice(..),
+                transaction.changes(),
+            )
+            .unwrap();
+
+        assert!(!ranges.is_empty());
+        // Only the edited line is reported; `fn a` and `fn c` are
+        // structurally unchanged.
+        let line_start = source.line_to_byte(1);
+        let line_end = source.line_to_byte(2);
+        assert!(
+            ranges
+                .iter()
+                .all(|range| range.start >= line_start && range.end <= line_end),
+            "unexpected changed ranges: {ranges:?}"
+        );
+    }
+
+    #[test]
+    fn test_parse_timeout_aborts_gracefully() {
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+
+        // A source large enough that parsing cannot finish within a
+        // microsecond.
+        let mut source = String::new();
+        for _ in 0..20_000 {
+            source.push_str("fn f() { let x = (1, [2, 3], \"s\"); }\n");
+        }
+        let source = Rope::from(source);
+
+        let syntax = Syntax::new_with_timeout(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+            std::time::Duration::from_micros(1),
+        );
+        assert!(syntax.is_none(), "parsing should abort, not hang");
+    }
+
+    #[test]
+    fn test_coalesce_events() {
+        use HighlightEvent::*;
+
+        // A redundant start/end/start/end sequence, as `merge` can emit
+        // for adjacent zero-width overlay spans. (`span_iter` itself no
+        // longer produces this shape for single-scope input since it
+        // pre-merges same-scope overlaps.)
+        let events = vec![
+            HighlightStart(Highlight(0)),
+            HighlightEnd,
+            HighlightStart(Highlight(0)),
+            HighlightEnd,
+        ];
+
+        let coalesced: Vec<_> = coalesce_events(events.into_iter()).collect();
+        assert_eq!(
+            coalesced,
+            vec![HighlightStart(Highlight(0)), HighlightEnd],
+            "the redundant zero-width pair should be removed"
+        );
+
+        // An intervening `Source` keeps the highlights separate.
+        let events = vec![
+            HighlightStart(Highlight(0)),
+            HighlightEnd,
+            Source { start: 2, end: 3 },
+            HighlightStart(Highlight(0)),
+            HighlightEnd,
+        ];
+        let coalesced: Vec<_> = coalesce_events(events.clone().into_iter()).collect();
+        assert_eq!(coalesced, events);
+
+        // Differing scopes are not coalesced either.
+        let events = vec![
+            HighlightStart(Highlight(0)),
+            HighlightEnd,
+            HighlightStart(Highlight(1)),
+            HighlightEnd,
+        ];
+        let coalesced: Vec<_> = coalesce_events(events.clone().into_iter()).collect();
+        assert_eq!(coalesced, events);
+    }
+
+    #[test]
+    fn test_byte_to_point() {
+        // 'é' is 2 bytes, '字' is 3.
+        let text = Rope::from("aé字b\ncd\n");
+
+        assert_eq!(byte_to_point(text.slice(..), 0), Point::new(0, 0));
+        // Columns are byte columns: positions after the multibyte
+        // characters advance by their UTF-8 lengths.
+        assert_eq!(byte_to_point(text.slice(..), 1), Point::new(0, 1));
+        assert_eq!(byte_to_point(text.slice(..), 3), Point::new(0, 3));
+        assert_eq!(byte_to_point(text.slice(..), 6), Point::new(0, 6));
+        // A newline resets the column and advances the row.
+        assert_eq!(byte_to_point(text.slice(..), 8), Point::new(1, 0));
+        assert_eq!(byte_to_point(text.slice(..), 9), Point::new(1, 1));
+        // The end of the text is a valid position.
+        assert_eq!(byte_to_point(text.slice(..), 11), Point::new(2, 0));
+    }
+
+    #[test]
+    fn test_input_edits() {
+        use tree_sitter::InputEdit;
+
+        let doc = Rope::from("hello world!\ntest 123");
+        let transaction = Transaction::change(
+            &doc,
+            vec![(6, 11, Some("test".into())), (12, 17, None)].into_iter(),
+        );
+        let edits = generate_edits(doc.slice(..), transaction.changes());
+        // transaction.apply(&mut state);
+
+        assert_eq!(
+            edits,
+            &[
+                InputEdit {
+                    start_byte: 6,
+                    old_end_byte: 11,
+                    new_end_byte: 10,
+                    start_position: Point { row: 0, column: 6 },
+                    old_end_position: Point { row: 0, column: 11 },
+                    new_end_position: Point { row: 0, column: 10 }
+                },
+                InputEdit {
+                    start_byte: 12,
+                    old_end_byte: 17,
+                    new_end_byte: 12,
+                    start_position: Point { row: 0, column: 12 },
+                    old_end_position: Point { row: 1, column: 4 },
+                    new_end_position: Point { row: 0, column: 12 }
+                }
+            ]
+        );
+
+        // Testing with the official example from tree-sitter
+        let mut doc = Rope::from("fn test() {}");
+        let transaction =
+            Transaction::change(&doc, vec![(8, 8, Some("a: u32".into()))].into_iter());
+        let edits = generate_edits(doc.slice(..), transaction.changes());
+        transaction.apply(&mut doc);
+
+        assert_eq!(doc, "fn test(a: u32) {}");
+        assert_eq!(
+            edits,
+            &[InputEdit {
+                start_byte: 8,
+                old_end_byte: 8,
+                new_end_byte: 14,
+                start_position: Point { row: 0, column: 8 },
+                old_end_position: Point { row: 0, column: 8 },
+                new_end_position: Point { row: 0, column: 14 }
+            }]
+        );
+    }
+
+    #[track_caller]
+    fn assert_pretty_print(
+        language_name: &str,
+        source: &str,
+        expected: &str,
+        start: usize,
+        end: usize,
+    ) {
+        let source = Rope::from_str(source);
+
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language(language_name).unwrap();
+
+        let config = HighlightConfiguration::new(
+            language,
+            language_name.to_string(),
+            "",
+            None,
+            None,
+            None,
+            "",
+            "",
+        )
+        .unwrap();
         let syntax = Syntax::new(
             source.slice(..),
             Arc::new(config),
@@ -0,0 +1,2093 @@
+//! Overlay highlight spans and their conversion into [`HighlightEvent`]
+//! streams.
+//!
+//! Syntax highlighting produces a stream of [`HighlightEvent`]s, but many
+//! overlays (search matches, diagnostics, selections) are more naturally
+//! described as a flat list of scoped byte ranges. This module defines
+//! [`Span`] for that purpose and provides adapters that turn span lists
+//! into event streams which compose with [`merge`](super::merge).
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+
+use helix_stdx::rope::{self, RopeSliceExt};
+
+use crate::selection::Selection;
+use crate::syntax::{Highlight, HighlightEvent, Syntax};
+use crate::RopeSlice;
+
+/// A byte range of the document tagged with a highlight scope.
+///
+/// The range is half-open: `start` is included, `end` is not. `scope` is an
+/// index into the theme's highlight list, like [`Highlight`]. `priority`
+/// breaks ties between spans covering the same range: higher-priority
+/// spans end up innermost in the [`span_iter`] output, so top-of-stack
+/// consumers see the more important highlight (e.g. a diagnostic over a
+/// selection). It defaults to `0`; see [`Span::with_priority`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub scope: usize,
+    pub start: usize,
+    pub end: usize,
+    pub priority: u8,
+}
+
+impl Span {
+    pub fn new(scope: usize, start: usize, end: usize) -> Self {
+        debug_assert!(start <= end);
+        Self {
+            scope,
+            start,
+            end,
+            priority: 0,
+        }
+    }
+
+    /// Set the overlap-tie-break priority; higher wins the top of the
+    /// highlight stack within a shared range.
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+impl Ord for Span {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Sort by start ascending and end *descending* so that a span sorts
+        // before any spans it contains. This is the order `span_iter`
+        // requires: outer spans must be opened before inner ones. Spans
+        // with identical ranges (e.g. duplicate diagnostics from multiple
+        // providers) are ordered by priority ascending — so higher
+        // priorities are opened later and sit innermost — and then by
+        // scope ascending so the resulting event stream does not depend on
+        // input insertion order.
+        self.start
+            .cmp(&other.start)
+            .then_with(|| other.end.cmp(&self.end))
+            .then_with(|| self.priority.cmp(&other.priority))
+            .then_with(|| self.scope.cmp(&other.scope))
+    }
+}
+
+impl PartialOrd for Span {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}@{}", self.start, self.end, self.scope)
+    }
+}
+
+/// Convert a sorted list of spans into a [`HighlightEvent`] stream.
+///
+/// Spans may overlap arbitrarily: a span which only partially overlaps an
+/// earlier span is split at the boundary so that the resulting highlights
+/// nest properly. Zero-width spans produce an adjacent
+/// `HighlightStart`/`HighlightEnd` pair with no `Source` in between. At
+/// equal positions, ends are emitted before starts.
+///
+/// The input must be sorted according to [`Span`]'s `Ord` implementation.
+///
+/// When every span shares one scope - selection highlighting commonly
+/// produces such lists - overlaps are merged into disjoint spans up
+/// front: with no second scope there is no stacking, so the iterator
+/// emits the merged, non-overlapping events through its cheap disjoint
+/// path instead of partitioning. The highlights are identical, the event
+/// stream just flatter.
+pub fn span_iter(spans: Vec<Span>) -> SpanIter<'static> {
+    span_iter_impl(merge_single_scope_spans(spans), None)
+}
+
+/// Collapse overlapping spans into disjoint ones when every span shares
+/// one scope, leaving mixed-scope input untouched.
+///
+/// Zero-width spans touching a merged span are absorbed by it; they
+/// would only re-open a scope that is already applied there.
+fn merge_single_scope_spans(spans: Vec<Span>) -> Vec<Span> {
+    if spans.windows(2).any(|pair| pair[0].scope != pair[1].scope) {
+        return spans;
+    }
+
+    let mut merged: Vec<Span> = Vec::with_capacity(spans.len());
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => merged.push(span),
+        }
+    }
+    merged
+}
+
+/// Like [`span_iter`], but restricted to spans intersecting `view`.
+///
+/// Spans ending before the viewport are dropped and - the input being
+/// sorted by start - everything from the first span starting at or past
+/// `view.end` is truncated up front, so off-screen spans cost no
+/// partitioning work during iteration. Partially-visible spans are kept
+/// whole: clipping their events to the viewport remains
+/// [`merge`](super::merge)'s job, exactly as for `span_iter`. A
+/// zero-width span at `view.start` is visible; one at `view.end` is
+/// off-screen.
+pub fn span_iter_within(mut spans: Vec<Span>, view: std::ops::Range<usize>) -> SpanIter<'static> {
+    debug_assert!(
+        spans.windows(2).all(|pair| pair[0] <= pair[1]),
+        "span_iter_within input must be sorted"
+    );
+    let cutoff = spans.partition_point(|span| span.start < view.end);
+    spans.truncate(cutoff);
+    spans.retain(|span| span.end > view.start || span.start == view.start);
+    span_iter(spans)
+}
+
+/// Like [`span_iter`], but checks `cancellation_flag` between spans.
+///
+/// When the flag becomes non-zero the iterator stops consuming spans and
+/// cleanly ends any open highlights, so the truncated stream still
+/// satisfies the usual event invariants. This mirrors the cancellation
+/// handling of `Syntax::highlight_iter`.
+pub fn span_iter_cancelable(
+    spans: Vec<Span>,
+    cancellation_flag: &std::sync::atomic::AtomicUsize,
+) -> SpanIter<'_> {
+    span_iter_impl(spans, Some(cancellation_flag))
+}
+
+fn span_iter_impl(
+    spans: Vec<Span>,
+    cancellation_flag: Option<&std::sync::atomic::AtomicUsize>,
+) -> SpanIter<'_> {
+    debug_assert!(
+        spans.windows(2).all(|pair| pair[0] <= pair[1]),
+        "span_iter input must be sorted"
+    );
+    SpanIter {
+        spans,
+        index: 0,
+        stack: Vec::new(),
+        pos: 0,
+        queue: std::collections::VecDeque::new(),
+        cancellation_flag,
+    }
+}
+
+/// Convert a sorted list of *non-overlapping* spans into a
+/// [`HighlightEvent`] stream.
+///
+/// This is a cheaper variant of [`span_iter`] for producers that guarantee
+/// their spans are disjoint, such as [`match_spans`]. Overlapping input is
+/// a bug in the caller and is only checked in debug builds.
+pub fn flat_span_iter(spans: Vec<Span>) -> impl Iterator<Item = HighlightEvent> {
+    use HighlightEvent::*;
+
+    debug_assert!(
+        spans
+            .windows(2)
+            .all(|pair| pair[0] <= pair[1] && pair[0].end <= pair[1].start),
+        "flat_span_iter input must be sorted and non-overlapping"
+    );
+
+    spans.into_iter().flat_map(|span| {
+        [
+            HighlightStart(Highlight::new_unchecked(span.scope)),
+            Source {
+                start: span.start,
+                end: span.end,
+            },
+            HighlightEnd,
+        ]
+        .into_iter()
+        // Zero-width spans carry no source text.
+        .filter(move |event| !matches!(event, Source { start, end } if start == end))
+    })
+}
+
+/// Like [`flat_span_iter`], but tolerant of overlapping input in all
+/// builds.
+///
+/// Overlap in a flat-span producer is a caller bug, but an overlay source
+/// misbehaving in a release build shouldn't yield events that break
+/// [`merge`](super::merge). Spans reaching into an earlier span are
+/// truncated to start at its end, and spans contained entirely within one
+/// are dropped, so the output always satisfies the non-overlap invariant.
+/// The input must still be sorted; [`flat_span_iter`] stays the choice
+/// for trusted producers.
+pub fn flat_span_iter_lenient(spans: Vec<Span>) -> impl Iterator<Item = HighlightEvent> {
+    let mut end = 0;
+    let sanitized: Vec<Span> = spans
+        .into_iter()
+        .filter_map(|mut span| {
+            if span.start < end {
+                span.start = end;
+                if span.start >= span.end {
+                    return None;
+                }
+            }
+            end = end.max(span.end);
+            Some(span)
+        })
+        .collect();
+    flat_span_iter(sanitized)
+}
+
+/// Iterator over the [`HighlightEvent`]s of a span list. See [`span_iter`].
+#[derive(Debug)]
+pub struct SpanIter<'a> {
+    spans: Vec<Span>,
+    index: usize,
+    // Currently open spans, innermost last. Nesting is guaranteed by the
+    // splitting in `process_next_span`, so ends are non-increasing from
+    // bottom to top.
+    stack: Vec<Span>,
+    // The position up to which `Source` events have been emitted.
+    pos: usize,
+    queue: std::collections::VecDeque<HighlightEvent>,
+    cancellation_flag: Option<&'a std::sync::atomic::AtomicUsize>,
+}
+
+impl SpanIter<'_> {
+    fn process_next_span(&mut self) {
+        use HighlightEvent::*;
+
+        let mut span = self.spans[self.index];
+        self.index += 1;
+
+        // Close any open spans which end at or before this span starts.
+        while let Some(&active) = self.stack.last() {
+            if active.end > span.start {
+                break;
+            }
+            if self.pos < active.end {
+                self.queue.push_back(Source {
+                    start: self.pos,
+                    end: active.end,
+                });
+                self.pos = active.end;
+            }
+            self.queue.push_back(HighlightEnd);
+            self.stack.pop();
+        }
+
+        // Cover the gap up to the new span. Outside of any span there is
+        // nothing to highlight, so no `Source` is emitted.
+        if !self.stack.is_empty() && self.pos < span.start {
+            self.queue.push_back(Source {
+                start: self.pos,
+                end: span.start,
+            });
+        }
+        self.pos = self.pos.max(span.start);
+
+        // If the new span outlives the innermost open span, split it at the
+        // boundary so the highlights nest: the inside part is emitted now
+        // and the remainder is re-queued in sorted position.
+        if let Some(&active) = self.stack.last() {
+            if span.end > active.end {
+                let remainder = Span {
+                    scope: span.scope,
+                    start: active.end,
+                    end: span.end,
+                    priority: span.priority,
+                };
+                span.end = active.end;
+                let insert_at =
+                    self.index + self.spans[self.index..].partition_point(|span| *span < remainder);
+                self.spans.insert(insert_at, remainder);
+            }
+        }
+
+        self.queue
+            .push_back(HighlightStart(Highlight::new_unchecked(span.scope)));
+        if span.start == span.end {
+            self.queue.push_back(HighlightEnd);
+        } else {
+            self.stack.push(span);
+        }
+    }
+}
+
+impl Iterator for SpanIter<'_> {
+    type Item = HighlightEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use HighlightEvent::*;
+
+        if let Some(event) = self.queue.pop_front() {
+            return Some(event);
+        }
+
+        // When cancelled, stop consuming spans and end the open highlights
+        // without emitting any further source text so the truncated stream
+        // remains well-formed.
+        if let Some(cancellation_flag) = self.cancellation_flag {
+            if self.index < self.spans.len()
+                && cancellation_flag.load(std::sync::atomic::Ordering::Relaxed) != 0
+            {
+                self.index = self.spans.len();
+                self.queue
+                    .extend(self.stack.drain(..).map(|_| HighlightEnd));
+                return self.queue.pop_front();
+            }
+        }
+
+        if self.index < self.spans.len() {
+            self.process_next_span();
+            return self.queue.pop_front();
+        }
+
+        // Close any spans left open.
+        if let Some(active) = self.stack.pop() {
+            if self.pos < active.end {
+                self.queue.push_back(Source {
+                    start: self.pos,
+                    end: active.end,
+                });
+                self.pos = active.end;
+            }
+            self.queue.push_back(HighlightEnd);
+            return self.queue.pop_front();
+        }
+
+        None
+    }
+}
+
+/// Collect all regex matches in `text` as spans with the given scope.
+///
+/// The returned spans are sorted and non-overlapping, ready for
+/// [`flat_span_iter`], and compose with [`merge`](super::merge) to overlay
+/// search highlights onto a syntax highlight stream. Zero-width matches
+/// (e.g. from `^` or `\b`) are skipped.
+pub fn match_spans(text: RopeSlice, regex: &rope::Regex, scope: usize) -> Vec<Span> {
+    regex
+        .find_iter(text.regex_input())
+        .filter(|mat| mat.start() < mat.end())
+        .map(|mat| Span::new(scope, mat.start(), mat.end()))
+        .collect()
+}
+
+/// Whole-word occurrences of `word` within `view`, as spans with the
+/// given scope.
+///
+/// This backs "highlight all occurrences of the word under the cursor":
+/// the returned spans are sorted and non-overlapping, ready for
+/// [`flat_span_iter`]. When a syntax tree is supplied, matches whose
+/// tightest node is a comment or string are skipped so prose mentions
+/// don't light up alongside code occurrences. `comment_nodes` and
+/// `string_nodes` name the node kinds treated as such; callers plumb them
+/// from [`LanguageConfiguration::comment_nodes`] and
+/// [`LanguageConfiguration::string_nodes`].
+///
+/// [`LanguageConfiguration::comment_nodes`]: super::LanguageConfiguration::comment_nodes
+/// [`LanguageConfiguration::string_nodes`]: super::LanguageConfiguration::string_nodes
+pub fn occurrence_spans(
+    text: RopeSlice,
+    word: &str,
+    view: std::ops::Range<usize>,
+    scope: usize,
+    syntax: Option<&Syntax>,
+    comment_nodes: &[&str],
+    string_nodes: &[&str],
+) -> Vec<Span> {
+    if word.is_empty() {
+        return Vec::new();
+    }
+    let pattern = format!(r"\b{}\b", regex::escape(word));
+    let regex = rope::Regex::new(&pattern).expect("an escaped word is a valid regex");
+    let mut cursor = syntax.map(|syntax| syntax.walk());
+
+    regex
+        .find_iter(text.regex_input_at_bytes(view))
+        .filter(|mat| mat.start() < mat.end())
+        .filter(|mat| {
+            let Some(cursor) = cursor.as_mut() else {
+                return true;
+            };
+            cursor.reset_to_byte_range(mat.start(), mat.end());
+            let kind = cursor.node().kind();
+            !comment_nodes.contains(&kind) && !string_nodes.contains(&kind)
+        })
+        .map(|mat| Span::new(scope, mat.start(), mat.end()))
+        .collect()
+}
+
+/// Spans of `scope` over whole-word occurrences of `keywords` inside
+/// comment nodes intersecting `view`.
+///
+/// This backs `TODO`/`FIXME`-style highlighting: only nodes whose kind is
+/// listed in `comment_nodes` - plumbed from
+/// [`LanguageConfiguration::comment_nodes`] - are scanned, so the keywords
+/// stay unhighlighted in code and strings. An occurrence bordered by an
+/// alphanumeric or underscore is skipped - `TODO` must not light up inside
+/// `TODOS` - and the result is sorted for [`span_iter`].
+///
+/// [`LanguageConfiguration::comment_nodes`]: super::LanguageConfiguration::comment_nodes
+pub fn comment_keyword_spans(
+    syntax: &Syntax,
+    text: RopeSlice,
+    keywords: &[&str],
+    scope: usize,
+    view: std::ops::Range<usize>,
+    comment_nodes: &[&str],
+) -> Vec<Span> {
+    fn is_word_char(ch: Option<char>) -> bool {
+        ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+    }
+
+    let mut spans = Vec::new();
+    let mut cursor = syntax.walk();
+
+    'dfs: loop {
+        let node = cursor.node();
+        let range = node.byte_range();
+        let is_comment = comment_nodes.contains(&node.kind());
+        if is_comment && range.start < view.end && range.end > view.start {
+            let comment: std::borrow::Cow<str> = text.byte_slice(range.clone()).into();
+            for keyword in keywords {
+                for (index, _) in comment.match_indices(keyword) {
+                    let before = comment[..index].chars().next_back();
+                    let after = comment[index + keyword.len()..].chars().next();
+                    if is_word_char(before) || is_word_char(after) {
+                        continue;
+                    }
+                    let start = range.start + index;
+                    spans.push(Span::new(scope, start, start + keyword.len()));
+                }
+            }
+        }
+
+        // Tokens nested inside a matched comment node would yield
+        // duplicate spans; don't descend into comments.
+        if !is_comment && cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.goto_next_sibling() {
+                continue 'dfs;
+            }
+            if !cursor.goto_parent() {
+                break 'dfs;
+            }
+        }
+    }
+
+    spans.sort_unstable();
+    spans
+}
+
+/// Spans for rendering a selection, with the cursors scoped separately.
+///
+/// Every selection range yields a span with `selection_scope` and a
+/// one-grapheme span under its cursor with `cursor_scope`
+/// (`primary_cursor_scope` for the range at index `primary`, which themes
+/// usually style differently). The selection span is split around the
+/// cursor so the output is sorted and non-overlapping, ready for
+/// [`flat_span_iter`]. A cursor at the end of the file has no grapheme to
+/// cover and yields a zero-width span.
+pub fn selection_spans(
+    text: RopeSlice,
+    selection: &Selection,
+    primary: usize,
+    selection_scope: usize,
+    cursor_scope: usize,
+    primary_cursor_scope: usize,
+) -> Vec<Span> {
+    use crate::graphemes::next_grapheme_boundary;
+
+    let mut spans = Vec::with_capacity(selection.len() * 2);
+
+    for (i, range) in selection.iter().enumerate() {
+        let cursor_scope = if i == primary {
+            primary_cursor_scope
+        } else {
+            cursor_scope
+        };
+
+        let cursor = range.cursor(text);
+        let cursor_start = text.char_to_byte(cursor);
+        let cursor_end = if cursor < text.len_chars() {
+            text.char_to_byte(next_grapheme_boundary(text, cursor))
+        } else {
+            // No grapheme to the right at EOF; the zero-width span still
+            // produces a highlight pair for block-cursor styling.
+            cursor_start
+        };
+
+        let start = text.char_to_byte(range.from());
+        let end = text.char_to_byte(range.to());
+
+        if start < cursor_start {
+            spans.push(Span::new(selection_scope, start, cursor_start));
+        }
+        spans.push(Span::new(cursor_scope, cursor_start, cursor_end));
+        if cursor_end < end {
+            spans.push(Span::new(selection_scope, cursor_end, end));
+        }
+    }
+
+    // Selection ranges are kept sorted but not necessarily in span order
+    // at shared boundaries.
+    spans.sort_unstable();
+    spans
+}
+
+/// Merge two span lists, each sorted by [`Span`]'s ordering, into one
+/// sorted list.
+///
+/// This is the O(n) alternative to concatenating and re-sorting when two
+/// independently produced lists (say, search matches and diagnostics) are
+/// combined for [`span_iter`]. Ties keep the elements of `a` before those
+/// of `b`.
+pub fn merge_sorted_spans(a: Vec<Span>, b: Vec<Span>) -> Vec<Span> {
+    debug_assert!(
+        a.windows(2).all(|pair| pair[0] <= pair[1]) && b.windows(2).all(|pair| pair[0] <= pair[1]),
+        "merge_sorted_spans inputs must be sorted"
+    );
+
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let mut a = a.into_iter().peekable();
+    let mut b = b.into_iter().peekable();
+    while let (Some(left), Some(right)) = (a.peek(), b.peek()) {
+        if right < left {
+            merged.push(b.next().unwrap());
+        } else {
+            merged.push(a.next().unwrap());
+        }
+    }
+    merged.extend(a);
+    merged.extend(b);
+    merged
+}
+
+/// Removes spans entirely covered by another span of the *same* scope.
+///
+/// A covered same-scope span contributes nothing to the highlights — the
+/// covering span already applies the scope across its range — but
+/// [`span_iter`] still partitions around it. Spans of different scopes
+/// are never removed, and the remaining spans keep their [`span_iter`]
+/// ordering. The input must be sorted.
+pub fn compact_spans(spans: Vec<Span>) -> Vec<Span> {
+    debug_assert!(
+        spans.windows(2).all(|pair| pair[0] <= pair[1]),
+        "compact_spans input must be sorted"
+    );
+
+    // Sorted input puts covering spans before the spans they cover, so a
+    // span is redundant exactly when an already-seen span of its scope
+    // reaches at least as far.
+    let mut max_end: HashMap<usize, usize> = HashMap::new();
+    let mut compacted = Vec::with_capacity(spans.len());
+    for span in spans {
+        match max_end.entry(span.scope) {
+            Entry::Occupied(mut entry) => {
+                if *entry.get() >= span.end {
+                    continue;
+                }
+                entry.insert(span.end);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(span.end);
+            }
+        }
+        compacted.push(span);
+    }
+    compacted
+}
+
+/// Compose the three rendering layers into one gap-free event stream.
+///
+/// `base` (syntax), `diagnostics` and `selections` are tiered by
+/// [`Span::with_priority`] in that order, so where ranges coincide the
+/// selection opens innermost, over the diagnostic, over the syntax
+/// highlight; spans that strictly contain one another nest by containment
+/// as usual. Each input must be sorted. Viewport regions in `0..view_len`
+/// not covered by any span are emitted as plain `Source` events so the
+/// whole viewport is painted. A zero-width highlight adjacent to
+/// uncovered text attaches to the end of the preceding covered region.
+pub fn compose_highlights(
+    base: Vec<Span>,
+    diagnostics: Vec<Span>,
+    selections: Vec<Span>,
+    view_len: usize,
+) -> Vec<HighlightEvent> {
+    use HighlightEvent::*;
+
+    fn tier(spans: Vec<Span>, priority: u8) -> Vec<Span> {
+        let mut spans: Vec<Span> = spans
+            .into_iter()
+            .map(|span| span.with_priority(priority))
+            .collect();
+        spans.sort_unstable();
+        spans
+    }
+
+    let spans = merge_sorted_spans(
+        merge_sorted_spans(tier(base, 0), tier(diagnostics, 1)),
+        tier(selections, 2),
+    );
+
+    let events: Vec<_> = span_iter(spans).collect();
+    let mut composed = Vec::with_capacity(events.len() + 2);
+    let mut pos = 0;
+    let mut depth = 0usize;
+
+    for (index, &event) in events.iter().enumerate() {
+        match event {
+            HighlightStart(_) => {
+                if depth == 0 {
+                    // Where does this top-level region's text begin?
+                    // Regions without any `Source` (zero-width
+                    // highlights) stay put at `pos`.
+                    let mut region_depth = 0usize;
+                    let region_start = events[index..]
+                        .iter()
+                        .find_map(|event| match event {
+                            HighlightStart(_) => {
+                                region_depth += 1;
+                                None
+                            }
+                            HighlightEnd => {
+                                region_depth -= 1;
+                                (region_depth == 0).then_some(None)
+                            }
+                            Source { start, .. } => Some(Some(*start)),
+                        })
+                        .flatten();
+                    if let Some(start) = region_start {
+                        if pos < start {
+                            composed.push(Source {
+                                start: pos,
+                                end: start,
+                            });
+                            pos = start;
+                        }
+                    }
+                }
+                depth += 1;
+                composed.push(event);
+            }
+            HighlightEnd => {
+                depth -= 1;
+                composed.push(event);
+            }
+            Source { end, .. } => {
+                pos = end;
+                composed.push(event);
+            }
+        }
+    }
+
+    if pos < view_len {
+        composed.push(Source {
+            start: pos,
+            end: view_len,
+        });
+    }
+    composed
+}
+
+/// Diagnostic overlay spans, scoped by severity.
+///
+/// `severity_scopes` maps severities to highlight scopes in
+/// `[hint, info, warning, error]` order. Diagnostic ranges are character
+/// indices and are converted to byte ranges against `text` here. The
+/// result is sorted for [`span_iter`]; overlapping diagnostics - including
+/// exact duplicates from multiple providers - are expected and handled by
+/// the splitting there.
+pub fn diagnostic_spans(
+    text: RopeSlice,
+    diagnostics: &[crate::diagnostic::Diagnostic],
+    severity_scopes: &[usize; 4],
+) -> Vec<Span> {
+    use crate::diagnostic::Severity;
+
+    let mut spans: Vec<Span> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let scope = severity_scopes[match diagnostic.severity() {
+                Severity::Hint => 0,
+                Severity::Info => 1,
+                Severity::Warning => 2,
+                Severity::Error => 3,
+            }];
+            Span::new(
+                scope,
+                text.char_to_byte(diagnostic.range.start),
+                text.char_to_byte(diagnostic.range.end),
+            )
+        })
+        .collect();
+    spans.sort_unstable();
+    spans
+}
+
+/// Rainbow-bracket spans for the viewport, colored by nesting depth.
+///
+/// Walks the `@rainbow.scope`/`@rainbow.bracket` captures of each layer's
+/// rainbow query and emits a span over every bracket within `view`, with
+/// `scope` set to the bracket's nesting depth modulo `colors`. The result
+/// is sorted and non-overlapping, ready for [`flat_span_iter`]. Unbalanced
+/// brackets in incomplete code simply highlight at their apparent depth.
+pub fn rainbow_spans(
+    syntax: &Syntax,
+    text: RopeSlice,
+    view: std::ops::Range<usize>,
+    colors: usize,
+) -> Vec<Span> {
+    let mut spans: Vec<Span> = syntax
+        .rainbow_spans(text, Some(view.clone()), colors)
+        .into_iter()
+        .map(|(scope, range)| Span::new(scope, range.start, range.end))
+        .filter(|span| span.start < view.end && span.end > view.start)
+        .collect();
+    spans.sort_unstable();
+    spans
+}
+
+/// Indentation-guide spans for the viewport, derived from the syntax tree.
+///
+/// Every named multi-line node crossing `view` contributes a guide at its
+/// own line's indentation column, drawn on the node's interior lines. The
+/// guide is emitted as a one-character span over the whitespace at that
+/// column; lines which are not indented that far (or where the column
+/// falls inside a tab) produce no span. `tab_width` and `indent_width`
+/// come from the language's indent configuration, so tab-indented files
+/// resolve to the correct columns. The result is sorted and
+/// non-overlapping, ready for [`flat_span_iter`].
+pub fn indent_guide_spans(
+    syntax: &Syntax,
+    text: RopeSlice,
+    view: std::ops::Range<usize>,
+    scope: usize,
+    tab_width: usize,
+    indent_width: usize,
+) -> Vec<Span> {
+    use crate::graphemes::tab_width_at;
+    use crate::indent::indent_level_for_line;
+
+    let view_start_line = text.byte_to_line(view.start.min(text.len_bytes()));
+    let view_end_line = text.byte_to_line(view.end.min(text.len_bytes()));
+
+    // `(line, visual column)` of every guide, deduplicated across the
+    // nested nodes that produce the same guide.
+    let mut guides = std::collections::BTreeSet::new();
+
+    let mut cursor = syntax.walk();
+    'outer: loop {
+        let node = cursor.node();
+        let intersects = node.start_byte() < view.end && node.end_byte() > view.start;
+        if intersects && node.is_named() {
+            let start_row = node.start_position().row;
+            let end_row = node.end_position().row;
+            if start_row < end_row {
+                let level = indent_level_for_line(text.line(start_row), tab_width, indent_width);
+                let first = (start_row + 1).max(view_start_line);
+                // The closing line sits at the guide column itself.
+                let last = end_row.min(view_end_line + 1);
+                for line in first..last {
+                    guides.insert((line, level * indent_width));
+                }
+            }
+        }
+        if intersects && cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.goto_next_sibling() {
+                continue 'outer;
+            }
+            if !cursor.goto_parent() {
+                break 'outer;
+            }
+        }
+    }
+
+    let mut spans = Vec::with_capacity(guides.len());
+    for (line, column) in guides {
+        let line_start = text.line_to_byte(line);
+        let mut width = 0;
+        let mut byte = 0;
+        for ch in text.line(line).chars() {
+            if width == column && (ch == ' ' || ch == '\t') {
+                let start = line_start + byte;
+                spans.push(Span::new(scope, start, start + ch.len_utf8()));
+                break;
+            }
+            match ch {
+                ' ' => width += 1,
+                '\t' => width += tab_width_at(width, tab_width as u16),
+                _ => break,
+            }
+            byte += ch.len_utf8();
+        }
+    }
+    spans
+}
+
+/// Expand each span outward to the nearest grapheme boundaries.
+///
+/// Byte-offset producers can land mid-grapheme — inside a multi-byte
+/// emoji or between a base character and its combining marks — which
+/// renders incorrectly. Snapping moves `start` down and `end` up to
+/// grapheme boundaries. Spans with the same scope which come to overlap
+/// through the expansion are merged, and the result is re-sorted so it
+/// remains valid [`span_iter`] input.
+pub fn snap_spans_to_graphemes(text: RopeSlice, spans: Vec<Span>) -> Vec<Span> {
+    use crate::graphemes::{
+        ensure_grapheme_boundary_prev, is_grapheme_boundary_byte, next_grapheme_boundary_byte,
+    };
+
+    let mut spans: Vec<Span> = spans
+        .into_iter()
+        .map(|mut span| {
+            if !is_grapheme_boundary_byte(text, span.start) {
+                // `byte_to_char` floors into the containing char, from
+                // where the boundary at or before it is a char index.
+                let char_idx = ensure_grapheme_boundary_prev(text, text.byte_to_char(span.start));
+                span.start = text.char_to_byte(char_idx);
+            }
+            if !is_grapheme_boundary_byte(text, span.end) {
+                // Floor into the containing char first: the grapheme
+                // helpers require a char boundary, and a mid-char byte
+                // would panic inside the segmenter. There is no char
+                // boundary between the floored position and `end`, so
+                // the next boundary is still the one at or after `end`.
+                let floored = text.char_to_byte(text.byte_to_char(span.end));
+                span.end = next_grapheme_boundary_byte(text, floored);
+            }
+            span
+        })
+        .collect();
+    spans.sort_unstable();
+
+    let mut snapped: Vec<Span> = Vec::with_capacity(spans.len());
+    for span in spans {
+        match snapped.last_mut() {
+            Some(last)
+                if last.scope == span.scope
+                    && last.priority == span.priority
+                    && span.start <= last.end =>
+            {
+                last.end = last.end.max(span.end);
+            }
+            _ => snapped.push(span),
+        }
+    }
+    // Growing a span's end during the merge can break the end-descending
+    // tie order with an equal-start span of another scope.
+    snapped.sort_unstable();
+    snapped
+}
+
+/// Split a span at line boundaries into one span per covered line.
+///
+/// Each produced span covers the part of `span` on one line, excluding
+/// the line ending. With `include_newline`, a line whose ending is inside
+/// `span` instead gets its span extended one char past the content, so a
+/// selection highlight can show the selected newline as a trailing cell.
+/// The result is sorted and non-overlapping, ready for
+/// [`flat_span_iter`].
+pub fn split_span_by_lines(text: RopeSlice, span: Span, include_newline: bool) -> Vec<Span> {
+    use crate::line_ending::{get_line_ending, line_end_byte_index};
+
+    let end = span.end.min(text.len_bytes());
+    if span.start >= end {
+        return Vec::new();
+    }
+
+    let start_line = text.byte_to_line(span.start);
+    // `end` is exclusive: the last covered line contains the byte before it.
+    let end_line = text.byte_to_line(end - 1);
+
+    let mut spans = Vec::with_capacity(end_line - start_line + 1);
+    for line in start_line..=end_line {
+        let line_start = text.line_to_byte(line);
+        let line_slice = text.line(line);
+        let content_end = line_end_byte_index(&text, line);
+        // How far into this line (ending included) the span reaches.
+        let covered_end = end.min(line_start + line_slice.len_bytes());
+
+        let seg_start = span.start.max(line_start);
+        let mut seg_end = covered_end.min(content_end);
+        if include_newline && covered_end > content_end {
+            if let Some(ending) = get_line_ending(&line_slice) {
+                let newline = ending.as_str().chars().next().unwrap();
+                seg_end = content_end + newline.len_utf8();
+            }
+        }
+
+        if seg_start < seg_end {
+            spans.push(Span::new(span.scope, seg_start, seg_end).with_priority(span.priority));
+        }
+    }
+    spans
+}
+
+/// The set of scopes highlighting each byte of a document, independent of
+/// the order and nesting of the events that produced them.
+///
+/// Two event streams which render identically produce equal sets, which
+/// makes this useful as a reference model in tests: scopes `0..128` are
+/// tracked as bits of a `u128` per byte.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HighlightSet {
+    bits: Vec<u128>,
+}
+
+impl HighlightSet {
+    /// The scope bitmask applied to the byte at `index`.
+    pub fn get(&self, index: usize) -> u128 {
+        self.bits.get(index).copied().unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// The positions at which two sets disagree, as
+    /// `(index, self_bits, other_bits)` tuples.
+    ///
+    /// When an equality assertion between two sets fails, this localizes
+    /// the disagreement to exact bytes and scope bits instead of a
+    /// whole-set diff.
+    pub fn difference(&self, other: &Self) -> Vec<(usize, u128, u128)> {
+        (0..self.len().max(other.len()))
+            .filter_map(|index| {
+                let (ours, theirs) = (self.get(index), other.get(index));
+                (ours != theirs).then_some((index, ours, theirs))
+            })
+            .collect()
+    }
+
+    /// Drop trailing bytes with no highlights so that sets covering
+    /// different document lengths compare equal when their highlights do.
+    pub fn trim(&mut self) {
+        while self.bits.last() == Some(&0) {
+            self.bits.pop();
+        }
+    }
+
+    /// Whether both sets apply the same sequence of scope masks to their
+    /// highlighted positions, ignoring where un-highlighted positions
+    /// fall.
+    ///
+    /// Sets built through different paths (events vs. spans) can disagree
+    /// only on interior gaps - positions one path records as zero bits
+    /// and the other skips entirely. Plain equality sees those as
+    /// different; this comparison treats zero and missing uniformly and
+    /// skips them, so cross-path comparisons stay robust.
+    pub fn eq_ignoring_empty(&self, other: &Self) -> bool {
+        self.bits
+            .iter()
+            .filter(|bits| **bits != 0)
+            .eq(other.bits.iter().filter(|bits| **bits != 0))
+    }
+
+    /// Build a set from an event stream, recording only positions within
+    /// `range`.
+    ///
+    /// `Source` regions are clipped to the window and regions entirely
+    /// outside it contribute nothing, so a viewport-limited stream can be
+    /// compared against the matching window of a full-document one.
+    pub fn from_events_in_range(
+        events: impl IntoIterator<Item = HighlightEvent>,
+        range: std::ops::Range<usize>,
+    ) -> Self {
+        let mut set = Self::default();
+        let mut stack: Vec<usize> = Vec::new();
+        let mut mask = 0u128;
+        for event in events {
+            match event {
+                HighlightEvent::HighlightStart(Highlight(scope)) => {
+                    stack.push(scope);
+                    mask |= 1 << (scope as u32 % 128);
+                }
+                HighlightEvent::HighlightEnd => {
+                    stack.pop();
+                    mask = stack
+                        .iter()
+                        .fold(0, |mask, scope| mask | 1 << (*scope as u32 % 128));
+                }
+                HighlightEvent::Source { start, end } => {
+                    set.mark(start.max(range.start), end.min(range.end), mask)
+                }
+            }
+        }
+        set.trim();
+        set
+    }
+
+    fn mark(&mut self, start: usize, end: usize, mask: u128) {
+        if mask == 0 || start >= end {
+            return;
+        }
+        if self.bits.len() < end {
+            self.bits.resize(end, 0);
+        }
+        for bits in &mut self.bits[start..end] {
+            *bits |= mask;
+        }
+    }
+}
+
+impl FromIterator<Span> for HighlightSet {
+    fn from_iter<T: IntoIterator<Item = Span>>(spans: T) -> Self {
+        let mut set = Self::default();
+        for span in spans {
+            set.mark(span.start, span.end, 1 << (span.scope as u32 % 128));
+        }
+        set.trim();
+        set
+    }
+}
+
+impl FromIterator<HighlightEvent> for HighlightSet {
+    fn from_iter<T: IntoIterator<Item = HighlightEvent>>(events: T) -> Self {
+        Self::from_events_in_range(events, 0..usize::MAX)
+    }
+}
+
+/// Flatten an event stream into `(range, highlights)` pairs.
+///
+/// Each `Source` region is paired with the highlights active over it,
+/// outermost first. Regions with no active highlights are skipped. This is
+/// the form renderers ultimately consume: a run of text plus the styles
+/// stacked on it, without the nesting of the event stream.
+pub fn styled_ranges(
+    events: impl IntoIterator<Item = HighlightEvent>,
+) -> Vec<(std::ops::Range<usize>, Vec<Highlight>)> {
+    let mut ranges = Vec::new();
+    let mut stack: Vec<Highlight> = Vec::new();
+    for event in events {
+        match event {
+            HighlightEvent::HighlightStart(highlight) => stack.push(highlight),
+            HighlightEvent::HighlightEnd => {
+                stack.pop();
+            }
+            HighlightEvent::Source { start, end } => {
+                if !stack.is_empty() && start < end {
+                    ranges.push((start..end, stack.clone()));
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// Lossily convert an event stream back into [`Span`]s.
+///
+/// Each `Source` region yields one span per highlight active over it,
+/// outermost to innermost. The result is not minimal — a highlight
+/// covering several `Source` regions comes back as one span per region —
+/// but it reconstructs an equivalent [`HighlightSet`], making arbitrary
+/// event streams inspectable and comparable as spans. The result is
+/// sorted, ready for [`span_iter`].
+pub fn events_to_spans(events: impl Iterator<Item = HighlightEvent>) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut stack: Vec<Highlight> = Vec::new();
+    for event in events {
+        match event {
+            HighlightEvent::HighlightStart(highlight) => stack.push(highlight),
+            HighlightEvent::HighlightEnd => {
+                stack.pop();
+            }
+            HighlightEvent::Source { start, end } => {
+                spans.extend(
+                    stack
+                        .iter()
+                        .map(|highlight| Span::new(highlight.0, start, end)),
+                );
+            }
+        }
+    }
+    spans.sort_unstable();
+    spans
+}
+
+impl FromIterator<(std::ops::Range<usize>, Vec<Highlight>)> for HighlightSet {
+    fn from_iter<T: IntoIterator<Item = (std::ops::Range<usize>, Vec<Highlight>)>>(
+        ranges: T,
+    ) -> Self {
+        let mut set = Self::default();
+        for (range, highlights) in ranges {
+            let mask = highlights.iter().fold(0u128, |mask, highlight| {
+                mask | 1 << (highlight.0 as u32 % 128)
+            });
+            set.mark(range.start, range.end, mask);
+        }
+        set.trim();
+        set
+    }
+}
+
+/// Panics unless `events` forms a well-formed highlight stream:
+/// `HighlightStart`/`HighlightEnd` are balanced and `Source` ranges are
+/// well-formed and strictly advance through the document.
+///
+/// Intended for tests and debugging of event stream producers.
+pub fn check_highlight_event_invariants(events: &[HighlightEvent]) {
+    let mut depth = 0usize;
+    let mut pos = 0;
+    for (index, event) in events.iter().enumerate() {
+        match *event {
+            HighlightEvent::HighlightStart(_) => depth += 1,
+            HighlightEvent::HighlightEnd => {
+                depth = depth
+                    .checked_sub(1)
+                    .unwrap_or_else(|| panic!("unbalanced HighlightEnd at event {index}"));
+            }
+            HighlightEvent::Source { start, end } => {
+                assert!(
+                    start <= end,
+                    "invalid Source range {start}..{end} at event {index}"
+                );
+                assert!(
+                    start >= pos,
+                    "Source {start}..{end} at event {index} moves backwards (position {pos})"
+                );
+                pos = end;
+            }
+        }
+    }
+    assert_eq!(depth, 0, "unbalanced HighlightStart");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Rope;
+    use HighlightEvent::*;
+
+    #[test]
+    fn test_match_spans() {
+        let source = Rope::from_str("fn main() {\n    let func = inner_fn;\n    fn nested() {}\n}");
+        let regex = rope::Regex::new(r"\bfn\b").unwrap();
+
+        let spans = match_spans(source.slice(..), &regex, 7);
+        assert_eq!(
+            spans,
+            vec![Span::new(7, 0, 2), Span::new(7, 41, 43)],
+            "only whole-word `fn` should match"
+        );
+
+        let events: Vec<_> = flat_span_iter(spans).collect();
+        check_highlight_event_invariants(&events);
+        assert_eq!(
+            events,
+            vec![
+                HighlightStart(Highlight(7)),
+                Source { start: 0, end: 2 },
+                HighlightEnd,
+                HighlightStart(Highlight(7)),
+                Source { start: 41, end: 43 },
+                HighlightEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_spans_skips_zero_width() {
+        let source = Rope::from_str("one\ntwo\n");
+        let regex = rope::Regex::new(r"^").unwrap();
+        assert!(match_spans(source.slice(..), &regex, 0).is_empty());
+    }
+
+    #[test]
+    fn test_diagnostic_spans_overlapping_duplicates() {
+        use crate::diagnostic::{Diagnostic, DiagnosticProvider, Severity};
+
+        fn diagnostic(start: usize, end: usize, severity: Severity) -> Diagnostic {
+            Diagnostic {
+                range: crate::diagnostic::Range { start, end },
+                ends_at_word: false,
+                starts_at_word: false,
+                zero_width: start == end,
+                line: 0,
+                message: String::new(),
+                severity: Some(severity),
+                code: None,
+                provider: DiagnosticProvider::default(),
+                tags: Vec::new(),
+                source: None,
+                data: None,
+            }
+        }
+
+        let text = Rope::from_str("let foo = bar;\n");
+        // Two providers reporting the same range at different severities,
+        // plus an overlapping hint - the rust-analyzer duplicate scenario.
+        let diagnostics = vec![
+            diagnostic(4, 7, Severity::Error),
+            diagnostic(4, 7, Severity::Warning),
+            diagnostic(6, 13, Severity::Hint),
+        ];
+
+        let spans = diagnostic_spans(text.slice(..), &diagnostics, &[0, 1, 2, 3]);
+        // The duplicates come first, ordered by scope ascending (the tie-
+        // break in `Span`'s ordering), the overlapping hint after them.
+        assert_eq!(
+            spans,
+            vec![Span::new(2, 4, 7), Span::new(3, 4, 7), Span::new(0, 6, 13)]
+        );
+
+        // The overlapping duplicates convert into a well-formed stream.
+        let events: Vec<_> = span_iter(spans).collect();
+        check_highlight_event_invariants(&events);
+    }
+
+    #[test]
+    fn test_equal_range_spans_sort_by_scope() {
+        // Duplicate ranges sort to the same order no matter how they were
+        // inserted, so the event stream is reproducible.
+        let mut forward = vec![Span::new(1, 2, 6), Span::new(4, 2, 6)];
+        let mut reversed = vec![Span::new(4, 2, 6), Span::new(1, 2, 6)];
+        forward.sort_unstable();
+        reversed.sort_unstable();
+        assert_eq!(forward, reversed);
+
+        let forward_events: Vec<_> = span_iter(forward).collect();
+        let reversed_events: Vec<_> = span_iter(reversed).collect();
+        assert_eq!(forward_events, reversed_events);
+        check_highlight_event_invariants(&forward_events);
+    }
+
+    #[test]
+    fn test_priority_wins_top_of_stack() {
+        use HighlightEvent::*;
+
+        // A diagnostic (scope 1, priority 1) and a selection (scope 5,
+        // priority 0) cover the same region; the diagnostic must sit
+        // innermost — and so win the top of the stack — even though its
+        // scope id sorts first.
+        let mut spans = vec![Span::new(1, 2, 6).with_priority(1), Span::new(5, 2, 6)];
+        spans.sort_unstable();
+        assert_eq!(
+            spans,
+            vec![Span::new(5, 2, 6), Span::new(1, 2, 6).with_priority(1)]
+        );
+
+        let events: Vec<_> = span_iter(spans).collect();
+        assert_eq!(
+            events,
+            vec![
+                HighlightStart(Highlight(5)),
+                HighlightStart(Highlight(1)),
+                Source { start: 2, end: 6 },
+                HighlightEnd,
+                HighlightEnd,
+            ]
+        );
+        check_highlight_event_invariants(&events);
+    }
+
+    #[test]
+    fn test_snap_spans_to_graphemes() {
+        // "a😀b": the emoji occupies bytes 1..5.
+        let text = Rope::from_str("a😀b");
+        let spans = snap_spans_to_graphemes(text.slice(..), vec![Span::new(0, 2, 3)]);
+        assert_eq!(spans, vec![Span::new(0, 1, 5)]);
+
+        // A span ending between a base character and its combining mark is
+        // at a char boundary but not a grapheme boundary: "e\u{301}"
+        // occupies bytes 1..4.
+        let text = Rope::from_str("ae\u{301}b");
+        let spans = snap_spans_to_graphemes(text.slice(..), vec![Span::new(0, 1, 2)]);
+        assert_eq!(spans, vec![Span::new(0, 1, 4)]);
+
+        // Same-scope spans which come to overlap through snapping merge.
+        let text = Rope::from_str("a😀b");
+        let spans =
+            snap_spans_to_graphemes(text.slice(..), vec![Span::new(0, 1, 2), Span::new(0, 3, 6)]);
+        assert_eq!(spans, vec![Span::new(0, 1, 6)]);
+    }
+
+    #[test]
+    fn test_flat_span_iter_lenient() {
+        // (1, 5, 15) reaches into the first span and is truncated; (2, 6, 8)
+        // is contained entirely and dropped.
+        let spans = vec![
+            Span::new(0, 0, 10),
+            Span::new(1, 5, 15),
+            Span::new(2, 6, 8),
+            Span::new(3, 15, 20),
+        ];
+        let events: Vec<_> = flat_span_iter_lenient(spans).collect();
+        let expected: Vec<_> = flat_span_iter(vec![
+            Span::new(0, 0, 10),
+            Span::new(1, 10, 15),
+            Span::new(3, 15, 20),
+        ])
+        .collect();
+        assert_eq!(events, expected);
+        check_highlight_event_invariants(&events);
+
+        // Non-overlapping input passes through untouched.
+        let spans = vec![Span::new(0, 0, 3), Span::new(1, 3, 6)];
+        let events: Vec<_> = flat_span_iter_lenient(spans.clone()).collect();
+        let expected: Vec<_> = flat_span_iter(spans).collect();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn test_split_span_by_lines() {
+        let text = Rope::from_str("one\ntwo\nthree\n");
+
+        // A three-line selection, with the selected newlines represented
+        // as one trailing char per line.
+        let spans = split_span_by_lines(text.slice(..), Span::new(0, 1, 12), true);
+        assert_eq!(
+            spans,
+            vec![Span::new(0, 1, 4), Span::new(0, 4, 8), Span::new(0, 8, 12)]
+        );
+        // The spans are valid `flat_span_iter` input.
+        let events: Vec<_> = flat_span_iter(spans).collect();
+        check_highlight_event_invariants(&events);
+
+        // Without the newline handling each line's span stops at the
+        // content.
+        let spans = split_span_by_lines(text.slice(..), Span::new(0, 1, 12), false);
+        assert_eq!(
+            spans,
+            vec![Span::new(0, 1, 3), Span::new(0, 4, 7), Span::new(0, 8, 12)]
+        );
+    }
+
+    #[test]
+    fn test_merge_sorted_spans() {
+        let a = vec![Span::new(0, 0, 10), Span::new(1, 4, 6), Span::new(2, 8, 9)];
+        let b = vec![
+            Span::new(3, 0, 10),
+            Span::new(4, 4, 6),
+            Span::new(5, 12, 14),
+        ];
+
+        let merged = merge_sorted_spans(a, b);
+        assert_eq!(
+            merged,
+            vec![
+                // Ties (equal start and end) keep `a`'s element first.
+                Span::new(0, 0, 10),
+                Span::new(3, 0, 10),
+                Span::new(1, 4, 6),
+                Span::new(4, 4, 6),
+                Span::new(2, 8, 9),
+                Span::new(5, 12, 14),
+            ]
+        );
+        assert!(merged.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // The merged list is directly consumable by `span_iter`.
+        let events: Vec<_> = span_iter(merged).collect();
+        check_highlight_event_invariants(&events);
+    }
+
+    #[test]
+    fn test_compact_spans() {
+        let spans = vec![
+            Span::new(0, 0, 10),
+            // Same scope and entirely covered: redundant.
+            Span::new(0, 2, 6),
+            // Entirely covered but a different scope: kept.
+            Span::new(1, 3, 5),
+            // Same scope but extends past the covering span: kept.
+            Span::new(0, 8, 12),
+        ];
+
+        let compacted = compact_spans(spans.clone());
+        assert_eq!(
+            compacted,
+            vec![Span::new(0, 0, 10), Span::new(1, 3, 5), Span::new(0, 8, 12)]
+        );
+
+        // Dropping covered same-scope spans must not change the highlights.
+        let before: HighlightSet = spans.into_iter().collect();
+        let after: HighlightSet = compacted.into_iter().collect();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn test_span_iter_within() {
+        let spans = vec![
+            // Entirely before the viewport.
+            Span::new(0, 0, 5),
+            // Partially visible at the viewport start: kept whole.
+            Span::new(1, 5, 12),
+            // Zero-width at the viewport start: visible.
+            Span::new(5, 10, 10),
+            // Entirely inside.
+            Span::new(2, 12, 15),
+            // Partially visible at the viewport end.
+            Span::new(3, 18, 25),
+            // Entirely after.
+            Span::new(4, 25, 30),
+        ];
+
+        let events: Vec<_> = span_iter_within(spans, 10..20).collect();
+        check_highlight_event_invariants(&events);
+
+        // Only the visible spans survive, with `span_iter`'s usual output
+        // for them.
+        let expected: Vec<_> = span_iter(vec![
+            Span::new(1, 5, 12),
+            Span::new(5, 10, 10),
+            Span::new(2, 12, 15),
+            Span::new(3, 18, 25),
+        ])
+        .collect();
+        assert_eq!(events, expected);
+        assert!(!events.contains(&HighlightStart(Highlight(0))));
+        assert!(!events.contains(&HighlightStart(Highlight(4))));
+    }
+
+    #[test]
+    fn test_eq_ignoring_empty() {
+        // The same masks with a gap at position 2 in one set only.
+        let gapped: HighlightSet = vec![Span::new(0, 0, 2), Span::new(1, 3, 5)]
+            .into_iter()
+            .collect();
+        let contiguous: HighlightSet = vec![Span::new(0, 0, 2), Span::new(1, 2, 4)]
+            .into_iter()
+            .collect();
+
+        // Plain equality sees the interior empty position...
+        assert_ne!(gapped, contiguous);
+        // ...but the lenient comparison skips it.
+        assert!(gapped.eq_ignoring_empty(&contiguous));
+        assert!(contiguous.eq_ignoring_empty(&gapped));
+
+        // Differing masks still compare unequal.
+        let different: HighlightSet = vec![Span::new(2, 0, 2), Span::new(1, 3, 5)]
+            .into_iter()
+            .collect();
+        assert!(!gapped.eq_ignoring_empty(&different));
+    }
+
+    #[test]
+    fn test_span_iter_nested() {
+        let events: Vec<_> = span_iter(vec![Span::new(0, 0, 10), Span::new(1, 3, 6)]).collect();
+        check_highlight_event_invariants(&events);
+        assert_eq!(
+            events,
+            vec![
+                HighlightStart(Highlight(0)),
+                Source { start: 0, end: 3 },
+                HighlightStart(Highlight(1)),
+                Source { start: 3, end: 6 },
+                HighlightEnd,
+                Source { start: 6, end: 10 },
+                HighlightEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_span_iter_partial_overlap_is_split() {
+        // The second span outlives the first and must be split at byte 6 so
+        // that the highlights nest.
+        let events: Vec<_> = span_iter(vec![Span::new(0, 0, 6), Span::new(1, 3, 10)]).collect();
+        check_highlight_event_invariants(&events);
+        assert_eq!(
+            events,
+            vec![
+                HighlightStart(Highlight(0)),
+                Source { start: 0, end: 3 },
+                HighlightStart(Highlight(1)),
+                Source { start: 3, end: 6 },
+                HighlightEnd,
+                HighlightEnd,
+                HighlightStart(Highlight(1)),
+                Source { start: 6, end: 10 },
+                HighlightEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_span_iter_zero_width() {
+        let events: Vec<_> = span_iter(vec![Span::new(0, 2, 2)]).collect();
+        check_highlight_event_invariants(&events);
+        assert_eq!(events, vec![HighlightStart(Highlight(0)), HighlightEnd]);
+    }
+
+    #[test]
+    fn test_span_iter_single_scope_merges_overlaps() {
+        let spans = vec![
+            Span::new(3, 0, 10),
+            Span::new(3, 5, 15),
+            // A zero-width span at a merged span's boundary is absorbed.
+            Span::new(3, 15, 15),
+            Span::new(3, 20, 25),
+        ];
+
+        let events: Vec<_> = span_iter(spans.clone()).collect();
+        check_highlight_event_invariants(&events);
+        // Overlaps collapse into disjoint events of the one scope...
+        assert_eq!(
+            events,
+            vec![
+                HighlightStart(Highlight(3)),
+                Source { start: 0, end: 15 },
+                HighlightEnd,
+                HighlightStart(Highlight(3)),
+                Source { start: 20, end: 25 },
+                HighlightEnd,
+            ]
+        );
+        // ...with exactly the highlights of the unmerged input.
+        let merged_set: HighlightSet = events.into_iter().collect();
+        let general_set: HighlightSet = spans.into_iter().collect();
+        assert_eq!(merged_set, general_set);
+    }
+
+    #[test]
+    fn test_span_iter_cancellation() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cancel = AtomicUsize::new(0);
+        let spans = vec![
+            Span::new(0, 0, 10),
+            Span::new(1, 2, 8),
+            Span::new(2, 12, 20),
+            Span::new(3, 22, 30),
+        ];
+        let mut iter = span_iter_cancelable(spans, &cancel);
+        let mut events: Vec<_> = iter.by_ref().take(3).collect();
+        cancel.store(1, Ordering::Relaxed);
+        events.extend(iter);
+
+        // The truncated stream is still well-formed...
+        check_highlight_event_invariants(&events);
+        // ...and spans past the cancellation point were never opened.
+        assert!(!events.contains(&HighlightStart(Highlight(2))));
+        assert!(!events.contains(&HighlightStart(Highlight(3))));
+    }
+
+    #[test]
+    fn test_rainbow_spans() {
+        use crate::syntax::{Configuration, HighlightConfiguration, Loader};
+        use arc_swap::ArcSwap;
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        let source = Rope::from_str("vec![foo(bar())]");
+        let rainbow_query = r#"
+        ((token_tree) @rainbow.scope)
+        (["(" ")" "[" "]"] @rainbow.bracket)
+        "#;
+
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+
+        let config = HighlightConfiguration::new(
+            language,
+            "rust".to_string(),
+            "",
+            None,
+            Some(rainbow_query),
+            None,
+            "",
+            "",
+        )
+        .unwrap();
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        let spans = rainbow_spans(&syntax, source.slice(..), 0..source.len_bytes(), 3);
+        assert_eq!(
+            spans,
+            vec![
+                // vec![foo(bar())]
+                Span::new(0, 4, 5),
+                Span::new(1, 8, 9),
+                Span::new(2, 12, 13),
+                Span::new(2, 13, 14),
+                Span::new(1, 14, 15),
+                Span::new(0, 15, 16),
+            ]
+        );
+
+        let events: Vec<_> = flat_span_iter(spans).collect();
+        check_highlight_event_invariants(&events);
+    }
+
+    #[test]
+    fn test_indent_guide_spans() {
+        use crate::syntax::{Configuration, HighlightConfiguration, Loader};
+        use arc_swap::ArcSwap;
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        let source = Rope::from_str("fn main() {\n    if true {\n        let x = 1;\n    }\n}\n");
+
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        let spans = indent_guide_spans(&syntax, source.slice(..), 0..source.len_bytes(), 5, 4, 4);
+        assert_eq!(
+            spans,
+            vec![
+                // column 0 of `    if true {`
+                Span::new(5, 12, 13),
+                // columns 0 and 4 of `        let x = 1;`
+                Span::new(5, 26, 27),
+                Span::new(5, 30, 31),
+                // column 0 of `    }`; the final `}` is not whitespace and
+                // gets no guide
+                Span::new(5, 45, 46),
+            ]
+        );
+
+        let events: Vec<_> = flat_span_iter(spans).collect();
+        check_highlight_event_invariants(&events);
+    }
+
+    #[test]
+    fn test_occurrence_spans() {
+        use crate::syntax::{
+            Configuration, HighlightConfiguration, Loader, DEFAULT_COMMENT_NODES,
+            DEFAULT_STRING_NODES,
+        };
+        use arc_swap::ArcSwap;
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        let source = Rope::from_str("fn foo() { let foo = 1; let s = \"foo\"; }");
+
+        // Without a syntax tree every whole-word match is reported.
+        let spans = occurrence_spans(
+            source.slice(..),
+            "foo",
+            0..source.len_bytes(),
+            7,
+            None,
+            DEFAULT_COMMENT_NODES,
+            DEFAULT_STRING_NODES,
+        );
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(7, 3, 6),
+                Span::new(7, 15, 18),
+                Span::new(7, 33, 36)
+            ]
+        );
+
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        // With one, the occurrence inside the string literal is skipped.
+        let spans = occurrence_spans(
+            source.slice(..),
+            "foo",
+            0..source.len_bytes(),
+            7,
+            Some(&syntax),
+            DEFAULT_COMMENT_NODES,
+            DEFAULT_STRING_NODES,
+        );
+        assert_eq!(spans, vec![Span::new(7, 3, 6), Span::new(7, 15, 18)]);
+
+        let events: Vec<_> = flat_span_iter(spans).collect();
+        check_highlight_event_invariants(&events);
+    }
+
+    #[test]
+    fn test_comment_keyword_spans() {
+        use crate::syntax::{Configuration, HighlightConfiguration, Loader, DEFAULT_COMMENT_NODES};
+        use arc_swap::ArcSwap;
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        let source = Rope::from_str("fn main() { let s = \"TODO\"; } // TODO: x, TODOS\n");
+        let loader = Loader::new(Configuration {
+            language: vec![],
+            language_server: HashMap::new(),
+            language_support_repo: vec![],
+        })
+        .unwrap();
+        let language = loader.grammars.get_language("rust").unwrap();
+        let config =
+            HighlightConfiguration::new(language, "rust".to_string(), "", None, None, None, "", "")
+                .unwrap();
+        let syntax = Syntax::new(
+            source.slice(..),
+            Arc::new(config),
+            Arc::new(ArcSwap::from_pointee(loader)),
+        )
+        .unwrap();
+
+        // Only the whole-word occurrence in the comment is reported: the
+        // string literal's TODO and the TODO inside TODOS are not.
+        let spans = comment_keyword_spans(
+            &syntax,
+            source.slice(..),
+            &["TODO", "FIXME"],
+            7,
+            0..source.len_bytes(),
+            DEFAULT_COMMENT_NODES,
+        );
+        assert_eq!(spans, vec![Span::new(7, 33, 37)]);
+
+        // Comments outside the viewport are skipped.
+        let spans = comment_keyword_spans(
+            &syntax,
+            source.slice(..),
+            &["TODO"],
+            7,
+            0..20,
+            DEFAULT_COMMENT_NODES,
+        );
+        assert!(spans.is_empty());
+    }
+
+    #[test]
+    fn test_selection_spans() {
+        use crate::selection::Range;
+        use smallvec::smallvec;
+
+        let text = Rope::from_str("abc def ghi\n");
+        let selection = Selection::new(
+            smallvec![
+                // Forward: the cursor sits on the last grapheme.
+                Range::new(0, 3),
+                // Backward: the cursor sits on the first grapheme.
+                Range::new(8, 5),
+                // A point selection at EOF has no grapheme to cover.
+                Range::point(12),
+            ],
+            0,
+        );
+
+        let spans = selection_spans(text.slice(..), &selection, 0, 1, 2, 3);
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(1, 0, 2),
+                Span::new(3, 2, 3),
+                Span::new(2, 5, 6),
+                Span::new(1, 6, 8),
+                Span::new(2, 12, 12),
+            ]
+        );
+
+        let events: Vec<_> = flat_span_iter(spans).collect();
+        check_highlight_event_invariants(&events);
+    }
+
+    #[test]
+    fn test_compose_highlights() {
+        use HighlightEvent::*;
+
+        // Syntax, a diagnostic and a selection partially overlapping, with
+        // the viewport extending past all of them.
+        let events = compose_highlights(
+            vec![Span::new(0, 0, 10)],
+            vec![Span::new(1, 4, 12)],
+            vec![Span::new(2, 8, 14)],
+            20,
+        );
+
+        assert_eq!(
+            events,
+            vec![
+                HighlightStart(Highlight(0)),
+                Source { start: 0, end: 4 },
+                HighlightStart(Highlight(1)),
+                Source { start: 4, end: 8 },
+                HighlightStart(Highlight(2)),
+                Source { start: 8, end: 10 },
+                HighlightEnd,
+                HighlightEnd,
+                HighlightEnd,
+                HighlightStart(Highlight(2)),
+                HighlightStart(Highlight(1)),
+                Source { start: 10, end: 12 },
+                HighlightEnd,
+                Source { start: 12, end: 14 },
+                HighlightEnd,
+                // The uncovered viewport tail is painted as a gap.
+                Source { start: 14, end: 20 },
+            ]
+        );
+        check_highlight_event_invariants(&events);
+
+        // A leading gap before the first covered region is filled too.
+        let events = compose_highlights(vec![Span::new(0, 5, 8)], Vec::new(), Vec::new(), 8);
+        assert_eq!(
+            events,
+            vec![
+                Source { start: 0, end: 5 },
+                HighlightStart(Highlight(0)),
+                Source { start: 5, end: 8 },
+                HighlightEnd,
+            ]
+        );
+        check_highlight_event_invariants(&events);
+    }
+
+    #[test]
+    fn test_highlight_set_from_events_in_range() {
+        let events = span_iter(vec![Span::new(0, 0, 20), Span::new(1, 8, 12)]);
+        let set = HighlightSet::from_events_in_range(events, 5..10);
+
+        // Positions outside the window are empty...
+        assert_eq!(set.get(4), 0);
+        assert_eq!(set.get(10), 0);
+        // ...and inside it both scopes are recorded where active.
+        assert_eq!(set.get(5), 0b01);
+        assert_eq!(set.get(9), 0b11);
+
+        // The windowed set equals the matching window of the full set.
+        let full: HighlightSet =
+            span_iter(vec![Span::new(0, 0, 20), Span::new(1, 8, 12)]).collect();
+        for index in 5..10 {
+            assert_eq!(set.get(index), full.get(index));
+        }
+    }
+
+    #[test]
+    fn test_highlight_set_difference() {
+        let left: HighlightSet = vec![Span::new(0, 0, 10)].into_iter().collect();
+        let right: HighlightSet = vec![Span::new(0, 0, 10), Span::new(1, 4, 5)]
+            .into_iter()
+            .collect();
+
+        assert!(left.difference(&left).is_empty());
+        // The disagreement is localized to byte 4 with the differing bits.
+        assert_eq!(left.difference(&right), vec![(4, 0b01, 0b11)]);
+        assert_eq!(right.difference(&left), vec![(4, 0b11, 0b01)]);
+    }
+
+    /// Build a sorted span list from arbitrary quickcheck input.
+    fn spans_from_triples(triples: &[(u8, u8, u8)]) -> Vec<Span> {
+        let mut spans: Vec<Span> = triples
+            .iter()
+            .map(|&(scope, a, b)| {
+                let (start, end) = if a <= b { (a, b) } else { (b, a) };
+                Span::new((scope % 8) as usize, start as usize, end as usize)
+            })
+            .collect();
+        spans.sort_unstable();
+        spans
+    }
+
+    /// Build a sorted, disjoint, non-empty span list from arbitrary
+    /// quickcheck input.
+    ///
+    /// `merge`'s overlay side only supports sorted non-overlapping
+    /// spans (overlapping inputs go through `span_iter` instead), so
+    /// overlapping triples are clipped against their predecessor and
+    /// spans emptied by clipping are dropped.
+    fn disjoint_spans_from_triples(triples: &[(u8, u8, u8)]) -> Vec<Span> {
+        let mut spans = spans_from_triples(triples);
+        let mut end = 0;
+        spans.retain_mut(|span| {
+            span.start = span.start.max(end);
+            if span.start >= span.end {
+                return false;
+            }
+            end = span.end;
+            true
+        });
+        spans
+    }
+
+    quickcheck::quickcheck! {
+        fn merge_produces_valid_event_stream(left: Vec<(u8, u8, u8)>, right: Vec<(u8, u8, u8)>) -> bool {
+            let left = spans_from_triples(&left);
+            let right = disjoint_spans_from_triples(&right);
+            let overlay: Vec<_> = right
+                .iter()
+                .map(|span| (span.scope, span.start..span.end))
+                .collect();
+
+            let events: Vec<_> = crate::syntax::merge(span_iter(left), overlay).collect();
+            check_highlight_event_invariants(&events);
+            true
+        }
+
+        fn styled_ranges_set_matches_events(spans: Vec<(u8, u8, u8)>) -> bool {
+            let spans = spans_from_triples(&spans);
+            let events: Vec<_> = span_iter(spans).collect();
+
+            let from_events: HighlightSet = events.iter().copied().collect();
+            let from_ranges: HighlightSet = styled_ranges(events).into_iter().collect();
+
+            let diff = from_events.difference(&from_ranges);
+            if !diff.is_empty() {
+                eprintln!("sets disagree at {:?}", &diff[..diff.len().min(5)]);
+            }
+            diff.is_empty()
+        }
+
+        fn merge_set_is_union_of_inputs(left: Vec<(u8, u8, u8)>, right: Vec<(u8, u8, u8)>) -> bool {
+            let mut left = spans_from_triples(&left);
+            // `merge` clips overlay spans to the source text covered by the
+            // base stream, so give the base full coverage to make the union
+            // property exact.
+            left.push(Span::new(0, 0, 256));
+            left.sort_unstable();
+            let right = disjoint_spans_from_triples(&right);
+
+            let overlay: Vec<_> = right
+                .iter()
+                .map(|span| (span.scope, span.start..span.end))
+                .collect();
+            let merged: HighlightSet =
+                crate::syntax::merge(span_iter(left.clone()), overlay).collect();
+            let union: HighlightSet = left.into_iter().chain(right).collect();
+
+            let diff = merged.difference(&union);
+            if !diff.is_empty() {
+                eprintln!("sets disagree at {:?}", &diff[..diff.len().min(5)]);
+            }
+            diff.is_empty()
+        }
+    }
+
+    /// Append a failing span list to `span_iter_regressions.txt`.
+    ///
+    /// Not called by any test: when a proptest in this module shrinks a
+    /// failure, call this from the failing property (or a scratch test)
+    /// to persist the shrunk case, then commit the updated corpus.
+    #[allow(dead_code)]
+    fn record_regression(mut spans: Vec<Span>) {
+        use std::io::Write;
+
+        spans.sort_unstable();
+        let line = spans
+            .iter()
+            .map(|span| format!("{},{},{}", span.scope, span.start, span.end))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/syntax/span_iter_regressions.txt"
+        );
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .expect("regression corpus exists");
+        writeln!(file, "{line}").expect("regression corpus append succeeds");
+    }
+
+    #[test]
+    fn test_span_iter_regression_corpus() {
+        let corpus = include_str!("span_iter_regressions.txt");
+
+        for (number, line) in corpus.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut spans: Vec<Span> = line
+                .split_whitespace()
+                .map(|triple| {
+                    let mut fields = triple.splitn(3, ',').map(|field| {
+                        field.parse::<usize>().unwrap_or_else(|_| {
+                            panic!("corpus line {}: malformed triple {triple:?}", number + 1)
+                        })
+                    });
+                    Span::new(
+                        fields.next().unwrap(),
+                        fields.next().unwrap(),
+                        fields.next().unwrap(),
+                    )
+                })
+                .collect();
+            spans.sort_unstable();
+
+            let events: Vec<_> = span_iter(spans.clone()).collect();
+            check_highlight_event_invariants(&events);
+
+            let from_spans: HighlightSet = spans.into_iter().collect();
+            let from_events: HighlightSet = events.into_iter().collect();
+            let diff = from_spans.difference(&from_events);
+            assert!(
+                diff.is_empty(),
+                "corpus line {}: sets disagree at {:?}",
+                number + 1,
+                &diff[..diff.len().min(5)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_events_to_spans_round_trip() {
+        // A simple overlapping pair: the inner span splits the outer one.
+        let spans = vec![Span::new(0, 0, 10), Span::new(1, 3, 6)];
+        let events: Vec<_> = span_iter(spans.clone()).collect();
+
+        let reconstructed = events_to_spans(events.into_iter());
+        // Not minimal: the outer scope comes back split around the inner
+        // span, one piece per `Source` region it covered.
+        assert_eq!(
+            reconstructed,
+            vec![
+                Span::new(0, 0, 3),
+                Span::new(0, 3, 6),
+                Span::new(1, 3, 6),
+                Span::new(0, 6, 10),
+            ]
+        );
+
+        // But the styled content is equivalent to the original spans.
+        let original: HighlightSet = spans.into_iter().collect();
+        let round_tripped: HighlightSet = reconstructed.into_iter().collect();
+        assert_eq!(original, round_tripped);
+    }
+
+    #[test]
+    fn test_highlight_set_agrees_between_spans_and_events() {
+        let spans = vec![Span::new(0, 0, 8), Span::new(1, 2, 12), Span::new(2, 4, 6)];
+        let from_spans: HighlightSet = spans.iter().copied().collect();
+        let from_events: HighlightSet = span_iter(spans).collect();
+        assert_eq!(from_spans, from_events);
+    }
+}
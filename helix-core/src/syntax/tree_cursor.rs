@@ -1,5 +1,8 @@
 use std::{cmp::Reverse, ops::Range};
 
+use crate::line_ending::line_end_byte_index;
+use crate::RopeSlice;
+
 use super::{LanguageLayer, LayerId};
 
 use slotmap::HopSlotMap;
@@ -18,6 +21,24 @@ struct InjectionRange {
     depth: u32,
 }
 
+/// The order injection ranges are searched in by the layer lookups.
+///
+/// Primarily by end ascending and depth descending, which lets the lookups
+/// start at a `partition_point` and prefer deeper layers. When two ranges
+/// tie on both - adjacent injections meeting at a boundary byte, or
+/// injections of different languages claiming the same range - the
+/// narrower range wins, then the earlier-allocated layer, so that layer
+/// selection at a contested byte is deterministic rather than depending
+/// on the layer map's iteration order.
+fn injection_range_sort_key(range: &InjectionRange) -> impl Ord {
+    (
+        range.end,
+        Reverse(range.depth),
+        Reverse(range.start),
+        range.layer_id,
+    )
+}
+
 pub struct TreeCursor<'a> {
     layers: &'a HopSlotMap<LayerId, LanguageLayer>,
     root: LayerId,
@@ -31,24 +52,7 @@ pub struct TreeCursor<'a> {
 impl<'a> TreeCursor<'a> {
     pub(super) fn new(layers: &'a HopSlotMap<LayerId, LanguageLayer>, root: LayerId) -> Self {
         let mut injection_ranges = Vec::new();
-
-        for (layer_id, layer) in layers.iter() {
-            // Skip the root layer
-            if layer.parent.is_none() {
-                continue;
-            }
-            for byte_range in layer.ranges.iter() {
-                let range = InjectionRange {
-                    start: byte_range.start_byte,
-                    end: byte_range.end_byte,
-                    layer_id,
-                    depth: layer.depth,
-                };
-                injection_ranges.push(range);
-            }
-        }
-
-        injection_ranges.sort_unstable_by_key(|range| (range.end, Reverse(range.depth)));
+        collect_injection_ranges(layers, &mut injection_ranges);
 
         let cursor = layers[root].tree().root_node();
 
@@ -61,6 +65,21 @@ impl<'a> TreeCursor<'a> {
         }
     }
 
+    /// Re-points the cursor at (the root of) another syntax tree.
+    ///
+    /// This is equivalent to `syntax.walk()` except that it reuses the
+    /// injection-range buffer of the existing cursor, so a long-lived
+    /// cursor can be moved between trees without reallocating on every
+    /// motion. The new syntax must live at least as long as the cursor's
+    /// lifetime parameter.
+    pub fn reset_to_syntax(&mut self, syntax: &'a super::Syntax) {
+        self.layers = &syntax.layers;
+        self.root = syntax.root;
+        self.current = syntax.root;
+        collect_injection_ranges(self.layers, &mut self.injection_ranges);
+        self.cursor = self.layers[self.root].tree().root_node();
+    }
+
     pub fn node(&self) -> Node<'a> {
         self.cursor
     }
@@ -210,11 +229,44 @@ impl<'a> TreeCursor<'a> {
     }
 
     pub fn reset_to_byte_range(&mut self, start: usize, end: usize) {
+        // Clamp out-of-bounds ranges to the tree's extent - they can occur
+        // when a stale view races with an edit - so the cursor lands on the
+        // closest node instead of panicking or falling back to the root.
+        let max = self.layers[self.root].tree().root_node().end_byte();
+        let (start, end) = (start.min(max), end.min(max));
         self.current = self.layer_id_containing_byte_range(start, end);
         let root = self.layers[self.current].tree().root_node();
         self.cursor = root.descendant_for_byte_range(start, end).unwrap_or(root);
     }
 
+    /// Positions the cursor at the tightest node containing `byte` across
+    /// injection layers and returns that node's kind.
+    ///
+    /// Returns `None` when `byte` is past the end of the document, which
+    /// makes this convenient for "inside: string"-style status line
+    /// context.
+    pub fn kind_at_byte(&mut self, byte: usize) -> Option<&'static str> {
+        if byte >= self.layers[self.root].tree().root_node().end_byte() {
+            return None;
+        }
+        self.reset_to_byte_range(byte, byte);
+        Some(self.node().kind())
+    }
+
+    /// Positions the cursor at the tightest node containing the `(row, col)`
+    /// point across injection layers.
+    ///
+    /// `col` is a byte column, as in editor events. Out-of-range points
+    /// clamp - a row past the end of the document lands on the last line
+    /// and a column past the end of a line lands at the line's end -
+    /// rather than panicking.
+    pub fn goto_point(&mut self, text: RopeSlice, row: usize, col: usize) {
+        let row = row.min(text.len_lines().saturating_sub(1));
+        let line_start = text.line_to_byte(row);
+        let byte = (line_start + col).min(line_end_byte_index(&text, row));
+        self.reset_to_byte_range(byte, byte);
+    }
+
     /// Returns an iterator over the children of the node the TreeCursor is on
     /// at the time this is called.
     pub fn children(&'a mut self) -> ChildIter {
@@ -240,6 +292,33 @@ impl<'a> TreeCursor<'a> {
     }
 }
 
+/// Collects and sorts the injection ranges of `layers` into
+/// `injection_ranges`, replacing its previous contents.
+fn collect_injection_ranges(
+    layers: &HopSlotMap<LayerId, LanguageLayer>,
+    injection_ranges: &mut Vec<InjectionRange>,
+) {
+    injection_ranges.clear();
+
+    for (layer_id, layer) in layers.iter() {
+        // Skip the root layer
+        if layer.parent.is_none() {
+            continue;
+        }
+        for byte_range in layer.ranges.iter() {
+            let range = InjectionRange {
+                start: byte_range.start_byte,
+                end: byte_range.end_byte,
+                layer_id,
+                depth: layer.depth,
+            };
+            injection_ranges.push(range);
+        }
+    }
+
+    injection_ranges.sort_unstable_by_key(injection_range_sort_key);
+}
+
 pub struct ChildIter<'n> {
     cursor: &'n mut TreeCursor<'n>,
     parent: Node<'n>,
@@ -262,3 +341,66 @@ impl<'n> Iterator for ChildIter<'n> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use slotmap::HopSlotMap;
+
+    use super::{injection_range_sort_key, InjectionRange, LayerId};
+
+    #[test]
+    fn adjacent_injections_sort_deterministically() {
+        let mut keys: HopSlotMap<LayerId, ()> = HopSlotMap::with_key();
+        let first = keys.insert(());
+        let second = keys.insert(());
+        let third = keys.insert(());
+
+        let mut ranges = vec![
+            // Two injections of the same depth sharing an end: the
+            // narrower one must come first regardless of input order.
+            InjectionRange {
+                start: 0,
+                end: 10,
+                layer_id: first,
+                depth: 1,
+            },
+            InjectionRange {
+                start: 5,
+                end: 10,
+                layer_id: second,
+                depth: 1,
+            },
+            // An identical range in another layer: the earlier-allocated
+            // layer comes first.
+            InjectionRange {
+                start: 5,
+                end: 10,
+                layer_id: third,
+                depth: 1,
+            },
+            // An adjacent injection beginning where the others end.
+            InjectionRange {
+                start: 10,
+                end: 20,
+                layer_id: first,
+                depth: 1,
+            },
+        ];
+        ranges.reverse();
+        ranges.sort_unstable_by_key(injection_range_sort_key);
+
+        let order: Vec<_> = ranges
+            .iter()
+            .map(|range| (range.start, range.end, range.layer_id))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (5, 10, second),
+                (5, 10, third),
+                (0, 10, first),
+                (10, 20, first),
+            ]
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use std::path::{Path, PathBuf};
 use std::str::from_utf8;
 
+use anyhow::{bail, Context, Result};
+
 /// Default built-in languages.toml.
 pub fn default_lang_config() -> toml::Value {
     let default_config = include_bytes!("../../languages.toml");
@@ -8,19 +11,16 @@ pub fn default_lang_config() -> toml::Value {
 }
 
 /// User configured languages.toml file, merged with the default config.
-pub fn user_lang_config() -> Result<toml::Value, toml::de::Error> {
+pub fn user_lang_config() -> Result<toml::Value> {
     let config = [
         crate::config_dir(),
         crate::find_workspace().0.join(".helix"),
     ]
     .into_iter()
     .map(|path| path.join("languages.toml"))
-    .filter_map(|file| {
-        std::fs::read_to_string(file)
-            .map(|config| toml::from_str(&config))
-            .ok()
-    })
-    .collect::<Result<Vec<_>, _>>()?
+    .filter(|file| file.exists())
+    .map(|file| load_lang_config_file(&file, &mut Vec::new()))
+    .collect::<Result<Vec<_>>>()?
     .into_iter()
     .fold(default_lang_config(), |a, b| {
         // combines for example
@@ -39,8 +39,126 @@ pub fn user_lang_config() -> Result<toml::Value, toml::de::Error> {
         //   language-server = { command = "/usr/bin/taplo" }
         //
         // thus it overrides the third depth-level of b with values of a if they exist, but otherwise merges their values
-        crate::merge_toml_values(a, b, 3)
+        //
+        // Arrays below the merge depth (like `file-types`) are appended to
+        // rather than replaced, so a user config can add a file-type with a
+        // one-element array.
+        crate::merge_toml_values_with(a, b, 3, crate::ArrayMergeStrategy::Append)
     });
 
     Ok(config)
 }
+
+/// Loads a `languages.toml` file, resolving its top-level `include` key.
+///
+/// `include = ["extra-languages.toml"]` entries are paths resolved relative
+/// to the including file and merged below it, so the including file wins
+/// where they overlap and later includes win over earlier ones. Includes
+/// may nest; `visited` tracks the chain of files currently being loaded so
+/// circular includes are rejected instead of recursing forever. (A file
+/// included twice via different non-circular paths is fine.)
+fn load_lang_config_file(file: &Path, visited: &mut Vec<PathBuf>) -> Result<toml::Value> {
+    let canonical = file
+        .canonicalize()
+        .with_context(|| format!("failed to resolve language config '{}'", file.display()))?;
+    if visited.contains(&canonical) {
+        bail!(
+            "circular include of language config '{}'",
+            canonical.display()
+        );
+    }
+    visited.push(canonical.clone());
+
+    let text = std::fs::read_to_string(&canonical)
+        .with_context(|| format!("failed to read language config '{}'", canonical.display()))?;
+    let mut config: toml::Value = toml::from_str(&text)
+        .with_context(|| format!("failed to parse language config '{}'", canonical.display()))?;
+
+    // The `include` key is resolved here and must not leak into the merged
+    // configuration.
+    let includes = match config
+        .as_table_mut()
+        .and_then(|table| table.remove("include"))
+    {
+        Some(toml::Value::Array(includes)) => includes,
+        Some(_) => bail!(
+            "'include' in '{}' must be an array of paths",
+            canonical.display()
+        ),
+        None => Vec::new(),
+    };
+
+    let dir = canonical
+        .parent()
+        .expect("a canonicalized file path has a parent");
+    let mut base: Option<toml::Value> = None;
+    for include in includes {
+        let Some(path) = include.as_str() else {
+            bail!(
+                "'include' in '{}' must be an array of paths",
+                canonical.display()
+            );
+        };
+        let included = load_lang_config_file(&dir.join(path), visited)?;
+        base = Some(match base {
+            Some(base) => {
+                crate::merge_toml_values_with(base, included, 3, crate::ArrayMergeStrategy::Append)
+            }
+            None => included,
+        });
+    }
+    if let Some(base) = base {
+        config = crate::merge_toml_values_with(base, config, 3, crate::ArrayMergeStrategy::Append);
+    }
+
+    visited.pop();
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::load_lang_config_file;
+
+    fn fixture_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("helix-lang-config-{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn simple_include_is_merged() {
+        let dir = fixture_dir("include");
+        std::fs::write(
+            dir.join("languages.toml"),
+            "include = [\"extra.toml\"]\n\n[[language]]\nname = \"alpha\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("extra.toml"), "[[language]]\nname = \"beta\"\n").unwrap();
+
+        let config = load_lang_config_file(&dir.join("languages.toml"), &mut Vec::new()).unwrap();
+        let table = config.as_table().unwrap();
+        assert!(!table.contains_key("include"));
+
+        let mut names: Vec<&str> = table["language"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|language| language["name"].as_str().unwrap())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["alpha", "beta"]);
+    }
+
+    #[test]
+    fn circular_include_is_rejected() {
+        let dir = fixture_dir("cycle");
+        std::fs::write(dir.join("a.toml"), "include = [\"b.toml\"]\n").unwrap();
+        std::fs::write(dir.join("b.toml"), "include = [\"a.toml\"]\n").unwrap();
+
+        let err = load_lang_config_file(&dir.join("a.toml"), &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("circular include"));
+    }
+}
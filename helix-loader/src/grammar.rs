@@ -7,7 +7,7 @@ use tree_sitter::Language;
 /// The location of a skidder repo.
 ///
 /// These can either be local paths or git repositories to fetch.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase", untagged)]
 pub enum Repository {
     Local {
@@ -70,6 +70,49 @@ impl fmt::Display for Repository {
     }
 }
 
+/// Why reading a runtime file for a grammar failed.
+///
+/// Callers can distinguish a grammar that no language support repo
+/// carries at all - worth prompting the user to fetch and build language
+/// support - from a grammar that merely lacks the requested file, and
+/// both from genuine IO failures such as permission errors.
+#[derive(Debug)]
+pub enum RuntimeFileError {
+    /// No language support repo contains a directory for the grammar.
+    MissingGrammarDir { grammar: String },
+    /// The grammar directory exists but the requested file is absent.
+    NotFound { path: PathBuf },
+    /// Reading the file failed for a reason other than absence.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl fmt::Display for RuntimeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingGrammarDir { grammar } => write!(
+                f,
+                "No directory found for grammar '{grammar}' in any language support repo"
+            ),
+            Self::NotFound { path } => write!(f, "Runtime file {} not found", path.display()),
+            Self::Io { path, source } => {
+                write!(f, "Failed to read file {}: {source}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuntimeFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Loader {
     config: skidder::Config,
@@ -99,11 +142,20 @@ impl Loader {
             config: skidder::Config {
                 repos,
                 index: crate::language_support_dir(),
-                verbose: true,
+                verbose: false,
             },
         }
     }
 
+    /// Stream build output (compiler invocations and their stdout/stderr)
+    /// instead of capturing it and only surfacing it on failure.
+    ///
+    /// Off by default; diagnosing slow or warning-laden grammar builds is
+    /// the intended use. Failing builds still error either way.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.config.verbose = verbose;
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn get_language(&self, _name: &str) -> Result<Language> {
         unimplemented!()
@@ -132,22 +184,28 @@ impl Loader {
                 .with_context(|| format!("Failed to load symbol {}", language_fn_name))?;
             language_fn()
         };
+        check_abi_version(&grammar, language.version())?;
         std::mem::forget(library);
         Ok(language)
     }
 
-    pub fn read_grammar_file(&self, grammar: &str, file: &str) -> Result<String> {
+    /// Whether a compiled parser for the grammar exists on disk in any
+    /// language support repo.
+    pub fn has_compiled_grammar(&self, name: &str) -> bool {
+        self.config.compiled_parser_path(name).is_some()
+    }
+
+    pub fn read_grammar_file(&self, grammar: &str, file: &str) -> Result<String, RuntimeFileError> {
         let Some(grammar_dir) = self.config.grammar_dir(grammar) else {
-            bail!(
-                "No file '{}' found for grammar '{}' in any language support repo",
-                file,
-                grammar
-            );
+            return Err(RuntimeFileError::MissingGrammarDir {
+                grammar: grammar.to_string(),
+            });
         };
         let path = grammar_dir.join(file);
-        let contents = std::fs::read_to_string(&path)
-            .with_context(|| format!("failed to read file {}", path.display()))?;
-        Ok(contents)
+        std::fs::read_to_string(&path).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => RuntimeFileError::NotFound { path },
+            _ => RuntimeFileError::Io { path, source: err },
+        })
     }
 
     pub fn repository_dirs(&self) -> impl Iterator<Item = (Repository, PathBuf)> + '_ {
@@ -158,11 +216,170 @@ impl Loader {
     }
 }
 
+/// Checks a loaded parser's ABI version against the range this build of
+/// tree-sitter supports.
+///
+/// A parser library compiled against an incompatible ABI loads fine via
+/// dlopen and only misbehaves - up to crashing - at parse time. Refusing
+/// it here turns a stale library left over from a tree-sitter upgrade
+/// into an actionable "rebuild language support" error instead.
+fn check_abi_version(grammar: &str, version: usize) -> Result<()> {
+    use tree_sitter::{LANGUAGE_VERSION, MIN_COMPATIBLE_LANGUAGE_VERSION};
+
+    if (MIN_COMPATIBLE_LANGUAGE_VERSION..=LANGUAGE_VERSION).contains(&version) {
+        Ok(())
+    } else {
+        bail!(
+            "The '{grammar}' parser was compiled for tree-sitter ABI version {version}, \
+             but this build supports versions {MIN_COMPATIBLE_LANGUAGE_VERSION} through \
+             {LANGUAGE_VERSION}. Update language support to rebuild it"
+        )
+    }
+}
+
+/// Checks that a working C compiler is available before starting any
+/// grammar builds.
+///
+/// Without this probe a missing toolchain only surfaces deep within the
+/// build of an individual grammar as an opaque "Failed to execute C
+/// compiler" error. The `HELIX_CC` environment variable overrides the
+/// list of compilers to probe.
+fn ensure_c_compiler() -> Result<()> {
+    use std::process::{Command, Stdio};
+
+    let candidates = match std::env::var("HELIX_CC") {
+        Ok(compiler) => vec![compiler],
+        Err(_) => ["cc", "gcc", "clang", "cl.exe"]
+            .iter()
+            .map(|compiler| compiler.to_string())
+            .collect(),
+    };
+
+    let found = candidates.iter().any(|compiler| {
+        Command::new(compiler)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    });
+
+    if found {
+        Ok(())
+    } else {
+        bail!(
+            "No C compiler found (tried {}). Install a C compiler such as gcc or clang in order to build tree-sitter parsers",
+            candidates.join(", ")
+        )
+    }
+}
+
 pub fn update_grammars(config: &Loader) -> Result<()> {
-    println!("Fetching language support...");
-    skidder::fetch(&config.config, true)?;
+    ensure_c_compiler()?;
+    // CI and airgapped builds set HELIX_GRAMMARS_OFFLINE to build whatever
+    // checkouts already exist without any network access.
+    if std::env::var_os("HELIX_GRAMMARS_OFFLINE").is_some() {
+        println!("HELIX_GRAMMARS_OFFLINE is set, skipping language support fetch...");
+    } else {
+        println!("Fetching language support...");
+        skidder::fetch(&config.config, true)?;
+    }
     println!("Building tree-sitter parsers...");
     skidder::build_all_grammars(&config.config, false, None)?;
     println!("Language support updated successfully");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{check_abi_version, ensure_c_compiler, Loader, Repository, RuntimeFileError};
+
+    quickcheck::quickcheck! {
+        /// The untagged `Repository` serde representation must stay
+        /// symmetric: whatever serializes to `languages.toml` syntax has
+        /// to deserialize back to the same variant. This guards the
+        /// config schema when variants or fields are added.
+        fn repository_roundtrips_through_toml(
+            local: bool,
+            name: String,
+            remote: String,
+            branch: String
+        ) -> bool {
+            let repository = if local {
+                Repository::Local {
+                    path: PathBuf::from(name),
+                }
+            } else {
+                Repository::Git { name, remote, branch }
+            };
+
+            let toml = toml::to_string(&repository).expect("repository serializes");
+            toml::from_str::<Repository>(&toml).expect("repository deserializes") == repository
+        }
+    }
+
+    #[test]
+    fn missing_compiler_is_reported() {
+        std::env::set_var("HELIX_CC", "/nonexistent/not-a-compiler");
+        let err = ensure_c_compiler().expect_err("bogus compiler should not be found");
+        assert!(
+            err.to_string().contains("No C compiler found"),
+            "unexpected error: {err}"
+        );
+        std::env::remove_var("HELIX_CC");
+    }
+
+    #[test]
+    fn incompatible_abi_is_refused() {
+        use tree_sitter::{LANGUAGE_VERSION, MIN_COMPATIBLE_LANGUAGE_VERSION};
+
+        // The supported range loads...
+        assert!(check_abi_version("rust", LANGUAGE_VERSION).is_ok());
+        assert!(check_abi_version("rust", MIN_COMPATIBLE_LANGUAGE_VERSION).is_ok());
+
+        // ...while parsers built under an older or newer ABI are refused
+        // with a pointer at rebuilding language support.
+        for version in [MIN_COMPATIBLE_LANGUAGE_VERSION - 1, LANGUAGE_VERSION + 1] {
+            let err = check_abi_version("rust", version)
+                .expect_err("out-of-range ABI version should be refused");
+            assert!(
+                err.to_string().contains("Update language support"),
+                "unexpected error: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn runtime_file_error_distinguishes_variants() {
+        let loader = Loader::new(&[]);
+
+        // A grammar no language support repo carries at all.
+        let err = loader
+            .read_grammar_file("definitely-not-a-grammar", "highlights.scm")
+            .expect_err("unknown grammar should not resolve");
+        assert!(
+            matches!(err, RuntimeFileError::MissingGrammarDir { ref grammar } if grammar == "definitely-not-a-grammar"),
+            "unexpected error: {err}"
+        );
+
+        // A known grammar missing the requested file.
+        let err = loader
+            .read_grammar_file("rust", "does-not-exist.scm")
+            .expect_err("missing file should error");
+        assert!(
+            matches!(err, RuntimeFileError::NotFound { .. }),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn builds_are_quiet_by_default() {
+        let mut loader = Loader::new(&[]);
+        assert!(!loader.config.verbose);
+        loader.set_verbose(true);
+        assert!(loader.config.verbose);
+    }
+}
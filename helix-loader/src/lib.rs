@@ -172,6 +172,18 @@ pub fn personal_dictionary_file() -> PathBuf {
     state_dir().join("personal-dictionary.txt")
 }
 
+/// How [merge_toml_values] combines arrays which exist in both documents
+/// once the merge depth is exhausted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMergeStrategy {
+    /// `right`'s array replaces `left`'s array wholesale.
+    #[default]
+    Replace,
+    /// `right`'s elements are appended to `left`'s array, skipping
+    /// elements that are already present.
+    Append,
+}
+
 /// Merge two TOML documents, merging values from `right` onto `left`
 ///
 /// When an array exists in both `left` and `right`, `right`'s array is
@@ -186,6 +198,24 @@ pub fn personal_dictionary_file() -> PathBuf {
 /// where one usually wants to override or add to the array instead of
 /// replacing it altogether.
 pub fn merge_toml_values(left: toml::Value, right: toml::Value, merge_depth: usize) -> toml::Value {
+    merge_toml_values_with(left, right, merge_depth, ArrayMergeStrategy::Replace)
+}
+
+/// Like [merge_toml_values], with control over what happens to arrays
+/// below the merge depth.
+///
+/// Arrays of tables with a `name` key (like `[[language]]` entries) are
+/// always merged entry-by-entry keyed on that name while the merge depth
+/// lasts; `strategy` decides the remaining arrays. With
+/// [ArrayMergeStrategy::Append] a user config can add a file-type to a
+/// language with `file-types = ["extra"]` instead of restating the whole
+/// array.
+pub fn merge_toml_values_with(
+    left: toml::Value,
+    right: toml::Value,
+    merge_depth: usize,
+    strategy: ArrayMergeStrategy,
+) -> toml::Value {
     use toml::Value;
 
     fn get_name(v: &Value) -> Option<&str> {
@@ -208,14 +238,27 @@ pub fn merge_toml_values(left: toml::Value, right: toml::Value, merge_depth: usi
                         })
                         .map(|lpos| left_items.remove(lpos));
                     let mvalue = match lvalue {
-                        Some(lvalue) => merge_toml_values(lvalue, rvalue, merge_depth - 1),
+                        Some(lvalue) => {
+                            merge_toml_values_with(lvalue, rvalue, merge_depth - 1, strategy)
+                        }
                         None => rvalue,
                     };
                     left_items.push(mvalue);
                 }
                 Value::Array(left_items)
             } else {
-                Value::Array(right_items)
+                match strategy {
+                    ArrayMergeStrategy::Replace => Value::Array(right_items),
+                    ArrayMergeStrategy::Append => {
+                        left_items.reserve(right_items.len());
+                        for rvalue in right_items {
+                            if !left_items.contains(&rvalue) {
+                                left_items.push(rvalue);
+                            }
+                        }
+                        Value::Array(left_items)
+                    }
+                }
             }
         }
         (Value::Table(mut left_map), Value::Table(right_map)) => {
@@ -223,7 +266,8 @@ pub fn merge_toml_values(left: toml::Value, right: toml::Value, merge_depth: usi
                 for (rname, rvalue) in right_map {
                     match left_map.remove(&rname) {
                         Some(lvalue) => {
-                            let merged_value = merge_toml_values(lvalue, rvalue, merge_depth - 1);
+                            let merged_value =
+                                merge_toml_values_with(lvalue, rvalue, merge_depth - 1, strategy);
                             left_map.insert(rname, merged_value);
                         }
                         None => {
@@ -279,7 +323,7 @@ fn ensure_parent_dir(path: &Path) {
 mod merge_toml_tests {
     use std::str;
 
-    use super::merge_toml_values;
+    use super::{merge_toml_values, merge_toml_values_with, ArrayMergeStrategy};
     use toml::Value;
 
     #[test]
@@ -346,4 +390,56 @@ mod merge_toml_tests {
             &vec![Value::String("lsp".into())]
         )
     }
+
+    #[test]
+    fn language_toml_file_types_append() {
+        const BASE: &str = r#"
+        [[language]]
+        name = "nix"
+        file-types = ["nix"]
+        "#;
+        const USER: &str = r#"
+        [[language]]
+        name = "nix"
+        file-types = ["nix", "nixpkg"]
+        "#;
+
+        let base: Value = toml::from_str(BASE).unwrap();
+        let user: Value = toml::from_str(USER).unwrap();
+
+        // The append strategy (used when merging `languages.toml`) adds the
+        // user's file-types without duplicating entries from the base.
+        let merged = merge_toml_values_with(base, user, 3, ArrayMergeStrategy::Append);
+        let nix = &merged.get("language").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            nix.get("file-types").unwrap().as_array().unwrap(),
+            &vec![Value::String("nix".into()), Value::String("nixpkg".into())]
+        );
+    }
+
+    #[test]
+    fn language_toml_file_types_replace_on_request() {
+        const BASE: &str = r#"
+        [[language]]
+        name = "nix"
+        file-types = ["nix"]
+        "#;
+        const USER: &str = r#"
+        [[language]]
+        name = "nix"
+        file-types = ["nixpkg"]
+        "#;
+
+        let base: Value = toml::from_str(BASE).unwrap();
+        let user: Value = toml::from_str(USER).unwrap();
+
+        // The replace strategy keeps the historical behavior: the user's
+        // array wins wholesale.
+        let merged = merge_toml_values_with(base, user, 3, ArrayMergeStrategy::Replace);
+        let nix = &merged.get("language").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            nix.get("file-types").unwrap().as_array().unwrap(),
+            &vec![Value::String("nixpkg".into())]
+        );
+    }
 }
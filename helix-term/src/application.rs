@@ -407,7 +407,7 @@ impl Application {
 
     /// refresh language config after config change
     fn refresh_language_config(&mut self) -> Result<(), Error> {
-        let lang_loader = helix_core::config::user_lang_loader()?;
+        let (lang_loader, warnings) = helix_core::config::user_lang_loader()?;
 
         self.syn_loader.store(Arc::new(lang_loader));
         self.editor.syn_loader = self.syn_loader.clone();
@@ -421,6 +421,12 @@ impl Application {
             document.replace_diagnostics(diagnostics, &[], None);
         }
 
+        // Dropped [[language]] entries are non-fatal but should not go
+        // unnoticed.
+        if !warnings.is_empty() {
+            self.editor.set_warning(warnings.join("; "));
+        }
+
         Ok(())
     }
 
@@ -541,6 +547,14 @@ impl Application {
     }
 
     pub async fn handle_idle_timeout(&mut self) {
+        // Pick up words another instance (or the user) wrote to the
+        // personal dictionary since we last looked.
+        match self.editor.reload_personal_dictionary_if_changed() {
+            Ok(true) => self.editor.needs_redraw = true,
+            Ok(false) => (),
+            Err(err) => log::error!("Failed to reload personal dictionary: {err}"),
+        }
+
         let mut cx = crate::compositor::Context {
             editor: &mut self.editor,
             jobs: &mut self.jobs,
@@ -134,16 +134,28 @@ FLAGS:
         }
     };
 
-    let lang_loader = helix_core::config::user_lang_loader().unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        eprintln!("Press <ENTER> to continue with default language config");
-        use std::io::Read;
-        // This waits for an enter press.
-        let _ = std::io::stdin().read(&mut []);
-        helix_core::config::default_lang_loader()
-    });
+    let mut lang_loader = match helix_core::config::user_lang_loader() {
+        Ok((loader, warnings)) => {
+            // Invalid [[language]] entries are dropped rather than fatal;
+            // say so instead of silently ignoring parts of the config.
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            loader
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("Press <ENTER> to continue with default language config");
+            use std::io::Read;
+            // This waits for an enter press.
+            let _ = std::io::stdin().read(&mut []);
+            helix_core::config::default_lang_loader()
+        }
+    };
 
     if args.update_grammars {
+        // `-v` streams the grammar builds' compiler output.
+        lang_loader.grammars_mut().set_verbose(args.verbosity > 0);
         helix_loader::grammar::update_grammars(lang_loader.grammars())?;
         return Ok(0);
     }
@@ -0,0 +1,120 @@
+//! Rendering of [`HighlightEvent`] streams as ANSI escape sequences.
+//!
+//! This is aimed at headless consumers — think piping highlighted source to
+//! a pager or asserting on colored output in tests — rather than the
+//! interactive terminal frontend, which renders through its own backend.
+
+use std::io::{self, Write};
+
+use helix_core::syntax::HighlightEvent;
+use helix_core::RopeSlice;
+
+use crate::graphics::Color;
+use crate::theme::Theme;
+
+/// The SGR parameter selecting `color`, for the foreground with `base` 30
+/// or the background with `base` 40. `Color::Reset` selects nothing.
+fn color_params(color: Color, base: u8) -> Option<String> {
+    let code = match color {
+        Color::Reset => return None,
+        Color::Black => base,
+        Color::Red => base + 1,
+        Color::Green => base + 2,
+        Color::Yellow => base + 3,
+        Color::Blue => base + 4,
+        Color::Magenta => base + 5,
+        Color::Cyan => base + 6,
+        Color::LightGray => base + 7,
+        // The bright variants live at 90-97 (foreground) and 100-107
+        // (background).
+        Color::Gray => base + 60,
+        Color::LightRed => base + 61,
+        Color::LightGreen => base + 62,
+        Color::LightYellow => base + 63,
+        Color::LightBlue => base + 64,
+        Color::LightMagenta => base + 65,
+        Color::LightCyan => base + 66,
+        Color::White => base + 67,
+        Color::Rgb(r, g, b) => return Some(format!("{};2;{r};{g};{b}", base + 8)),
+        Color::Indexed(i) => return Some(format!("{};5;{i}", base + 8)),
+    };
+    Some(code.to_string())
+}
+
+/// Write the text covered by `events` to `out`, coloring each `Source`
+/// region with the theme style of its innermost active highlight.
+///
+/// Only the foreground and background colors are rendered. Note that text
+/// not covered by any `Source` event is not written: overlay streams such
+/// as [`span_iter`](helix_core::syntax::span_iter) skip unhighlighted gaps,
+/// while `highlight_iter` streams cover the whole document.
+pub fn write_ansi(
+    events: impl IntoIterator<Item = HighlightEvent>,
+    text: RopeSlice,
+    theme: &Theme,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut stack = Vec::new();
+
+    for event in events {
+        match event {
+            HighlightEvent::HighlightStart(highlight) => stack.push(highlight),
+            HighlightEvent::HighlightEnd => {
+                stack.pop();
+            }
+            HighlightEvent::Source { start, end } => {
+                let mut params = Vec::new();
+                if let Some(highlight) = stack.last() {
+                    let style = theme.highlight(highlight.0);
+                    params.extend(style.fg.and_then(|fg| color_params(fg, 30)));
+                    params.extend(style.bg.and_then(|bg| color_params(bg, 40)));
+                }
+
+                if !params.is_empty() {
+                    write!(out, "\x1b[{}m", params.join(";"))?;
+                }
+                for chunk in text.byte_slice(start..end).chunks() {
+                    out.write_all(chunk.as_bytes())?;
+                }
+                if !params.is_empty() {
+                    write!(out, "\x1b[0m")?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use helix_core::syntax::{Highlight, HighlightEvent};
+    use helix_core::Rope;
+
+    use super::write_ansi;
+    use crate::theme::Theme;
+
+    #[test]
+    fn test_write_ansi_keyword_color() {
+        use HighlightEvent::*;
+
+        let theme: Theme = toml::from_str(r##""keyword" = "#ff0000""##).unwrap();
+        let keyword = theme.find_scope_index_exact("keyword").unwrap();
+
+        let text = Rope::from_str("fn main");
+        let events = [
+            HighlightStart(Highlight(keyword)),
+            Source { start: 0, end: 2 },
+            HighlightEnd,
+            Source { start: 2, end: 7 },
+        ];
+
+        let mut out = Vec::new();
+        write_ansi(events, text.slice(..), &theme, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            // The keyword is colored; the unhighlighted rest is plain.
+            "\x1b[38;2;255;0;0mfn\x1b[0m main"
+        );
+    }
+}
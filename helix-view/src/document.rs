@@ -1189,7 +1189,15 @@ impl Document {
     ) {
         if let (Some(language_config), Some(loader)) = (language_config, loader) {
             if let Some(highlight_config) = language_config.highlight_config(&loader.load()) {
-                self.syntax = Syntax::new(self.text.slice(..), highlight_config, loader);
+                let max_bytes = language_config
+                    .max_file_size
+                    .unwrap_or(helix_core::syntax::DEFAULT_MAX_FILE_SIZE);
+                self.syntax = Syntax::new_with_max_bytes(
+                    self.text.slice(..),
+                    highlight_config,
+                    loader,
+                    max_bytes,
+                );
             }
 
             self.language = Some(language_config);
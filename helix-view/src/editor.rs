@@ -1099,6 +1099,12 @@ pub struct Editor {
 
     /// HACK:
     pub dictionary: Dictionary,
+    /// Modification time of the personal dictionary when it was last
+    /// loaded, used to pick up external changes to the file.
+    personal_dictionary_mtime: Option<std::time::SystemTime>,
+    /// The personal dictionary entries currently loaded into
+    /// `dictionary`, used to detect removals on reload.
+    personal_dictionary_words: HashSet<String>,
 }
 
 pub type Motion = Box<dyn Fn(&mut Editor)>;
@@ -1164,6 +1170,47 @@ pub enum CloseError {
     SaveError(anyhow::Error),
 }
 
+/// Reads the on-disk personal dictionary into a set of words.
+///
+/// Returns the words and the file's modification time, or `None` when no
+/// personal dictionary exists yet.
+fn read_personal_dictionary(
+    path: &Path,
+) -> anyhow::Result<Option<(HashSet<String>, std::time::SystemTime)>> {
+    use std::io::{BufRead as _, BufReader};
+
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mtime = file.metadata()?.modified()?;
+    let reader = BufReader::with_capacity(8 * 1024, file);
+    let mut words = HashSet::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        words.insert(line.to_string());
+    }
+    Ok(Some((words, mtime)))
+}
+
+/// Adds personal dictionary entries to `dictionary`.
+fn add_personal_words<'a>(
+    dictionary: &mut Dictionary,
+    words: impl Iterator<Item = &'a String>,
+) -> anyhow::Result<()> {
+    for word in words {
+        dictionary
+            .add(word)
+            .map_err(|err| anyhow::anyhow!("failed to add {word:?} to the dictionary: {err}"))?;
+    }
+    Ok(())
+}
+
 impl Editor {
     pub fn new(
         mut area: Rect,
@@ -1189,18 +1236,21 @@ impl Editor {
                 .unwrap();
         // HACK: All this stuff should happen off the main thread.
         let mut dictionary = Dictionary::new(&aff, &dic).unwrap();
-        if let Ok(file) = std::fs::File::open(helix_loader::personal_dictionary_file()) {
-            use std::io::{BufRead as _, BufReader};
-            let reader = BufReader::with_capacity(8 * 1024, file);
-            for line in reader.lines() {
-                let line = line.unwrap();
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
+        let mut personal_dictionary_words = HashSet::new();
+        let mut personal_dictionary_mtime = None;
+        // A present-but-unreadable personal dictionary (e.g. permission
+        // denied) shouldn't prevent the editor from starting; spell
+        // checking just runs without the personal words.
+        match read_personal_dictionary(&helix_loader::personal_dictionary_file()) {
+            Ok(Some((words, mtime))) => {
+                if let Err(err) = add_personal_words(&mut dictionary, words.iter()) {
+                    log::warn!("Failed to add personal dictionary words: {err}");
                 }
-
-                dictionary.add(line).unwrap();
+                personal_dictionary_words = words;
+                personal_dictionary_mtime = Some(mtime);
             }
+            Ok(None) => (),
+            Err(err) => log::warn!("Failed to read personal dictionary: {err}"),
         }
 
         Self {
@@ -1242,9 +1292,59 @@ impl Editor {
             mouse_down_range: None,
             cursor_cache: CursorCache::default(),
             dictionary,
+            personal_dictionary_mtime,
+            personal_dictionary_words,
         }
     }
 
+    /// Re-reads the personal dictionary when the file changed on disk.
+    ///
+    /// Another editor instance adding a word (or the user editing the
+    /// file directly) updates the on-disk list without this process
+    /// noticing; this is polled on the idle timer to keep the cached
+    /// dictionary consistent. Appended words are added in place; when
+    /// words were removed from the file the dictionary is rebuilt from
+    /// the bundled files, since entries cannot be removed in place.
+    /// Returns whether a reload happened.
+    pub fn reload_personal_dictionary_if_changed(&mut self) -> anyhow::Result<bool> {
+        let path = helix_loader::personal_dictionary_file();
+        let mtime = match std::fs::metadata(&path) {
+            Ok(metadata) => Some(metadata.modified()?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if mtime == self.personal_dictionary_mtime {
+            return Ok(false);
+        }
+
+        let (words, mtime) = match read_personal_dictionary(&path)? {
+            Some((words, mtime)) => (words, Some(mtime)),
+            // The file was deleted out from under us.
+            None => (HashSet::new(), None),
+        };
+
+        if words.is_superset(&self.personal_dictionary_words) {
+            add_personal_words(
+                &mut self.dictionary,
+                words.difference(&self.personal_dictionary_words),
+            )?;
+        } else {
+            let aff = std::fs::read_to_string(helix_loader::runtime_file(
+                "dictionaries/en_US/en_US.aff",
+            ))?;
+            let dic = std::fs::read_to_string(helix_loader::runtime_file(
+                "dictionaries/en_US/en_US.dic",
+            ))?;
+            let mut dictionary = Dictionary::new(&aff, &dic)
+                .map_err(|err| anyhow::anyhow!("failed to load dictionary: {err}"))?;
+            add_personal_words(&mut dictionary, words.iter())?;
+            self.dictionary = dictionary;
+        }
+        self.personal_dictionary_words = words;
+        self.personal_dictionary_mtime = mtime;
+        Ok(true)
+    }
+
     pub fn popup_border(&self) -> bool {
         self.config().popup_border == PopupBorderConfig::All
             || self.config().popup_border == PopupBorderConfig::Popup
@@ -2286,3 +2386,40 @@ impl CursorCache {
         self.0.set(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{add_personal_words, read_personal_dictionary, Dictionary};
+
+    #[test]
+    fn externally_appended_word_is_accepted_after_reload() {
+        use std::io::Write as _;
+
+        let dir = std::env::temp_dir().join(format!("helix-personal-dict-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("personal-dictionary.txt");
+        std::fs::write(&path, "helix\n").unwrap();
+
+        let mut dictionary = Dictionary::new("", "1\nhello\n").unwrap();
+        let (words, _) = read_personal_dictionary(&path).unwrap().unwrap();
+        add_personal_words(&mut dictionary, words.iter()).unwrap();
+        assert!(dictionary.check("helix"));
+        assert!(!dictionary.check("spellbook"));
+
+        // Another instance appends a word; the poll sees a superset of
+        // the loaded entries and adds only the new word in place.
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writeln!(file, "spellbook").unwrap();
+        drop(file);
+
+        let (new_words, _) = read_personal_dictionary(&path).unwrap().unwrap();
+        assert!(new_words.is_superset(&words));
+        add_personal_words(&mut dictionary, new_words.difference(&words)).unwrap();
+        assert!(dictionary.check("spellbook"));
+        assert!(dictionary.check("helix"));
+    }
+}
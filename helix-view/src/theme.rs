@@ -35,6 +35,25 @@ pub static BASE16_DEFAULT_THEME: Lazy<Theme> = Lazy::new(|| Theme {
     ..Theme::from(BASE16_DEFAULT_THEME_DATA.clone())
 });
 
+/// An issue encountered while loading a theme.
+///
+/// `scope` names the theme key the issue applies to when it can be
+/// attributed to one; palette-wide failures carry no scope.
+#[derive(Clone, Debug)]
+pub struct ThemeWarning {
+    pub scope: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ThemeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.scope {
+            Some(scope) => write!(f, "{scope}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Loader {
     /// Theme directories to search from highest to lowest priority
@@ -51,6 +70,16 @@ impl Loader {
         }
     }
 
+    /// Creates a new loader that searches the given theme directories as-is,
+    /// without appending a "themes" subdirectory.
+    ///
+    /// Like [`Loader::new`] the directories are ordered from highest to
+    /// lowest priority. This is useful for validating an arbitrary directory
+    /// of theme files, such as `cargo xtask theme-check <dir>`.
+    pub fn with_theme_dirs(theme_dirs: Vec<PathBuf>) -> Self {
+        Self { theme_dirs }
+    }
+
     /// Loads a theme searching directories in priority order.
     pub fn load(&self, name: &str) -> Result<Theme> {
         let (theme, warnings) = self.load_with_warnings(name)?;
@@ -63,7 +92,7 @@ impl Loader {
     }
 
     /// Loads a theme searching directories in priority order, returning any warnings
-    pub fn load_with_warnings(&self, name: &str) -> Result<(Theme, Vec<String>)> {
+    pub fn load_with_warnings(&self, name: &str) -> Result<(Theme, Vec<ThemeWarning>)> {
         if name == "default" {
             return Ok((self.default(), Vec::new()));
         }
@@ -262,7 +291,7 @@ fn build_theme_values(
     Vec<String>,
     Vec<Style>,
     usize,
-    Vec<String>,
+    Vec<ThemeWarning>,
 ) {
     let mut styles = HashMap::new();
     let mut scopes = Vec::new();
@@ -276,7 +305,10 @@ fn build_theme_values(
         .remove("palette")
         .map(|value| {
             ThemePalette::try_from(value).unwrap_or_else(|err| {
-                warnings.push(err);
+                warnings.push(ThemeWarning {
+                    scope: None,
+                    message: err,
+                });
                 ThemePalette::default()
             })
         })
@@ -292,7 +324,10 @@ fn build_theme_values(
         .and_then(|value| match palette.parse_style_array(value) {
             Ok(styles) => Some(styles),
             Err(err) => {
-                warnings.push(err);
+                warnings.push(ThemeWarning {
+                    scope: Some("rainbow".to_string()),
+                    message: err,
+                });
                 None
             }
         })
@@ -310,7 +345,10 @@ fn build_theme_values(
     for (name, style_value) in values {
         let mut style = Style::default();
         if let Err(err) = palette.parse_style(&mut style, style_value) {
-            warnings.push(err);
+            warnings.push(ThemeWarning {
+                scope: Some(name.clone()),
+                message: err,
+            });
         }
 
         // these are used both as UI and as highlights
@@ -390,7 +428,7 @@ impl Theme {
         self.rainbow_length
     }
 
-    fn from_toml(value: Value) -> (Self, Vec<String>) {
+    fn from_toml(value: Value) -> (Self, Vec<ThemeWarning>) {
         if let Value::Table(table) = value {
             Theme::from_keys(table)
         } else {
@@ -399,7 +437,7 @@ impl Theme {
         }
     }
 
-    fn from_keys(toml_keys: Map<String, Value>) -> (Self, Vec<String>) {
+    fn from_keys(toml_keys: Map<String, Value>) -> (Self, Vec<ThemeWarning>) {
         let (styles, scopes, highlights, rainbow_length, load_errors) =
             build_theme_values(toml_keys);
 
@@ -0,0 +1,85 @@
+use helix_loader::grammar::Repository;
+
+use crate::DynError;
+
+/// Whether a git revision is pinned to an exact commit (a 40-char hex SHA)
+/// rather than a branch or tag, which can drift between builds.
+fn is_pinned_revision(revision: &str) -> bool {
+    revision.len() == 40 && revision.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The git repositories in `repos` whose branch is not a pinned commit,
+/// rendered as `name ('branch')` for reporting.
+fn unpinned_repositories(repos: &[Repository]) -> Vec<String> {
+    repos
+        .iter()
+        .filter_map(|repo| match repo {
+            Repository::Git { name, branch, .. } if !is_pinned_revision(branch) => {
+                Some(format!("{name} ('{branch}')"))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn audit_grammars() -> Result<(), DynError> {
+    let config = crate::helpers::lang_config();
+    let unpinned = unpinned_repositories(&config.language_support_repo);
+
+    if unpinned.is_empty() {
+        println!("All configured grammar repositories are pinned to a commit");
+        return Ok(());
+    }
+
+    for repo in &unpinned {
+        println!("Grammar repository {repo} is not pinned to a commit");
+    }
+    Err(format!(
+        "{} grammar repositories track a branch or tag instead of a commit",
+        unpinned.len()
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use helix_core::syntax::Configuration;
+
+    use super::{is_pinned_revision, unpinned_repositories};
+
+    #[test]
+    fn branch_pinned_repository_is_flagged() {
+        let config: Configuration = toml::from_str(
+            r#"
+            language = []
+
+            [[language-support-repo]]
+            name = "drifting"
+            remote = "https://example.com/drifting-grammars"
+            branch = "main"
+
+            [[language-support-repo]]
+            name = "pinned"
+            remote = "https://example.com/pinned-grammars"
+            branch = "d5f704548c9c5f9e5f40ab2a6e7c3cdb8db58b80"
+            "#,
+        )
+        .unwrap();
+
+        let unpinned = unpinned_repositories(&config.language_support_repo);
+        assert_eq!(unpinned, vec!["drifting ('main')".to_string()]);
+    }
+
+    #[test]
+    fn pinned_revision_detection() {
+        assert!(is_pinned_revision(
+            "d5f704548c9c5f9e5f40ab2a6e7c3cdb8db58b80"
+        ));
+        // Too short, non-hex and tag-like revisions are all unpinned.
+        assert!(!is_pinned_revision("d5f7045"));
+        assert!(!is_pinned_revision(
+            "g5f704548c9c5f9e5f40ab2a6e7c3cdb8db58b80"
+        ));
+        assert!(!is_pinned_revision("v1.0.0"));
+    }
+}
@@ -7,6 +7,7 @@ use std::fs;
 
 pub const TYPABLE_COMMANDS_MD_OUTPUT: &str = "typable-cmd.md";
 pub const LANG_SUPPORT_MD_OUTPUT: &str = "lang-support.md";
+pub const GRAMMAR_SOURCES_MD_OUTPUT: &str = "grammar-sources.md";
 
 fn md_table_heading(cols: &[String]) -> String {
     let mut header = String::new();
@@ -23,6 +24,32 @@ fn md_mono(s: &str) -> String {
     format!("`{}`", s)
 }
 
+/// The standard captures a `textobjects.scm` may define, as documented in
+/// the book's textobject guide.
+const TEXTOBJECT_CAPTURES: &[&str] = &[
+    "function.inside",
+    "function.around",
+    "class.inside",
+    "class.around",
+    "test.inside",
+    "test.around",
+    "parameter.inside",
+    "comment.inside",
+    "comment.around",
+    "entry.inside",
+    "entry.around",
+];
+
+/// Returns the standard textobject captures defined in `query`, in the
+/// order of [`TEXTOBJECT_CAPTURES`].
+fn textobject_captures(query: &str) -> Vec<&'static str> {
+    TEXTOBJECT_CAPTURES
+        .iter()
+        .copied()
+        .filter(|capture| query.contains(&format!("@{capture}")))
+        .collect()
+}
+
 pub fn typable_commands() -> Result<String, DynError> {
     let mut md = String::new();
     md.push_str(&md_table_heading(&[
@@ -51,17 +78,21 @@ pub fn lang_features() -> Result<String, DynError> {
     let mut md = String::new();
 
     let mut cols = vec!["Language".to_owned()];
+    cols.push("Grammar built".to_owned());
+    cols.push("Textobjects".to_owned());
     cols.push("Default LSP".to_owned());
 
     md.push_str(&md_table_heading(&cols));
     let config = helpers::lang_config();
-
-    let mut langs = config
-        .language
-        .iter()
-        .map(|l| l.language_id.clone())
+    let grammars = helix_loader::grammar::Loader::new(&config.language_support_repo);
+    // The syntax loader resolves `; inherits` directives when reading the
+    // runtime queries below.
+    let query_loader = helix_core::syntax::Loader::new(helpers::lang_config())?;
+
+    let langs = config
+        .language_ids()
+        .map(|id| id.to_owned())
         .collect::<Vec<_>>();
-    langs.sort_unstable();
 
     let mut row = Vec::new();
     for lang in langs {
@@ -72,6 +103,28 @@ pub fn lang_features() -> Result<String, DynError> {
             .unwrap(); // lang comes from config
         row.push(lc.language_id.clone());
 
+        // Report whether the grammar library actually exists on disk so that
+        // the generated table reflects reality after a partial build.
+        let grammar_name = lc.grammar.as_deref().unwrap_or(&lc.language_id);
+        row.push(if grammars.has_compiled_grammar(grammar_name) {
+            "✓".to_owned()
+        } else {
+            String::new()
+        });
+
+        // Queries that are missing entirely produce an empty cell, like a
+        // missing grammar.
+        let captures = helix_core::syntax::read_query(&query_loader, &lang, "textobjects.scm")
+            .map(|query| textobject_captures(&query))
+            .unwrap_or_default();
+        row.push(
+            captures
+                .iter()
+                .map(|capture| md_mono(capture))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+
         let mut seen_commands = HashSet::new();
         let mut commands = String::new();
         for ls_config in lc
@@ -99,8 +152,61 @@ pub fn lang_features() -> Result<String, DynError> {
     Ok(md)
 }
 
+/// Generates a table of the language support repositories that grammars and
+/// queries are fetched from, for packagers and auditors.
+pub fn grammar_sources() -> Result<String, DynError> {
+    use helix_loader::grammar::{Loader, Repository};
+
+    let mut md = String::new();
+    md.push_str(&md_table_heading(&[
+        "Repository".to_owned(),
+        "Source".to_owned(),
+        "Branch".to_owned(),
+    ]));
+
+    let config = helpers::lang_config();
+    let loader = Loader::new(&config.language_support_repo);
+
+    for (repository, _dir) in loader.repository_dirs() {
+        let row = match repository {
+            Repository::Local { path } => vec![
+                "(local)".to_owned(),
+                md_mono(&path.display().to_string()),
+                String::new(),
+            ],
+            Repository::Git {
+                name,
+                remote,
+                branch,
+            } => vec![name, md_mono(&remote), md_mono(&branch)],
+        };
+        md.push_str(&md_table_row(&row));
+    }
+
+    Ok(md)
+}
+
 pub fn write(filename: &str, data: &str) {
     let error = format!("Could not write to {}", filename);
     let path = path::book_gen().join(filename);
     fs::write(path, data).expect(&error);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers;
+
+    use super::textobject_captures;
+
+    #[test]
+    fn rust_function_around_is_reported() {
+        let loader = helix_core::syntax::Loader::new(helpers::lang_config()).unwrap();
+        let query = helix_core::syntax::read_query(&loader, "rust", "textobjects.scm").unwrap();
+
+        let captures = textobject_captures(&query);
+        assert!(
+            captures.contains(&"function.around"),
+            "rust textobjects should define function.around, got {captures:?}"
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use serde_json::{json, Value};
+
+use crate::{path, DynError};
+
+/// A JSON schema describing `languages.toml`.
+///
+/// The schema is maintained by hand against `Configuration` and
+/// `LanguageConfiguration` in `helix-core/src/syntax.rs` (generating it
+/// from the types would require a `schemars` dependency on the editor
+/// crates). Every key `LanguageConfiguration` accepts must be described
+/// here - `schema_covers_every_language_configuration_field` fails
+/// otherwise - but the value shapes are looser than the serde types;
+/// unknown keys are still rejected at load time by serde.
+pub fn languages_json_schema() -> Value {
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Helix languages.toml",
+        "type": "object",
+        "properties": {
+            "language": {
+                "type": "array",
+                "items": { "$ref": "#/definitions/language" },
+            },
+            "language-server": {
+                "type": "object",
+                "additionalProperties": { "$ref": "#/definitions/language-server" },
+            },
+            "language-support-repo": {
+                "type": "array",
+                "items": { "$ref": "#/definitions/repository" },
+            },
+            // Resolved by helix-loader before the strict parse; paths are
+            // relative to the including file.
+            "include": { "type": "array", "items": { "type": "string" } },
+        },
+        "definitions": {
+            "language": {
+                "type": "object",
+                "required": ["name", "scope"],
+                "properties": {
+                    "name": { "type": "string" },
+                    "language-id": { "type": "string" },
+                    "scope": { "type": "string" },
+                    "file-types": {
+                        "type": "array",
+                        "items": {
+                            "anyOf": [
+                                { "type": "string" },
+                                {
+                                    "type": "object",
+                                    "properties": { "glob": { "type": "string" } },
+                                    "required": ["glob"],
+                                },
+                            ],
+                        },
+                    },
+                    "shebangs": { "type": "array", "items": { "type": "string" } },
+                    "roots": { "type": "array", "items": { "type": "string" } },
+                    "comment-tokens": { "$ref": "#/definitions/comment-tokens" },
+                    // serde alias for `comment-tokens`.
+                    "comment-token": { "$ref": "#/definitions/comment-tokens" },
+                    "block-comment-tokens": {
+                        "anyOf": [
+                            { "$ref": "#/definitions/block-comment-token" },
+                            {
+                                "type": "array",
+                                "items": { "$ref": "#/definitions/block-comment-token" },
+                            },
+                        ],
+                    },
+                    "text-width": { "type": "integer", "minimum": 0 },
+                    "soft-wrap": {
+                        "type": "object",
+                        "properties": {
+                            "enable": { "type": "boolean" },
+                            "max-wrap": { "type": "integer", "minimum": 0 },
+                            "max-indent-retain": { "type": "integer", "minimum": 0 },
+                            "wrap-indicator": { "type": "string" },
+                            "wrap-at-text-width": { "type": "boolean" },
+                        },
+                    },
+                    "auto-format": { "type": "boolean" },
+                    "formatter": {
+                        "type": "object",
+                        "required": ["command"],
+                        "properties": {
+                            "command": { "type": "string" },
+                            "args": { "type": "array", "items": { "type": "string" } },
+                        },
+                    },
+                    "path-completion": { "type": "boolean" },
+                    "diagnostic-severity": {
+                        "enum": ["hint", "info", "warning", "error"],
+                    },
+                    "grammar": { "type": "string" },
+                    "injection-regex": { "type": "string" },
+                    "language-servers": {
+                        "type": "array",
+                        "items": {
+                            "anyOf": [
+                                { "type": "string" },
+                                { "type": "object" },
+                            ],
+                        },
+                    },
+                    "indent": {
+                        "type": "object",
+                        "required": ["tab-width", "unit"],
+                        "properties": {
+                            "tab-width": { "type": "integer", "minimum": 1 },
+                            "unit": { "type": "string" },
+                        },
+                    },
+                    "debugger": { "type": "object" },
+                    "auto-pairs": {
+                        "anyOf": [
+                            { "type": "boolean" },
+                            {
+                                "type": "object",
+                                "additionalProperties": { "type": "string" },
+                            },
+                        ],
+                    },
+                    "rulers": {
+                        "type": "array",
+                        "items": { "type": "integer", "minimum": 0 },
+                    },
+                    "workspace-lsp-roots": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                    },
+                    "rainbow-brackets": { "type": "boolean" },
+                    "max-file-size": { "type": "integer", "minimum": 0 },
+                    "persistent-diagnostic-sources": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                    },
+                    "comment-nodes": { "type": "array", "items": { "type": "string" } },
+                    "string-nodes": { "type": "array", "items": { "type": "string" } },
+                },
+            },
+            "comment-tokens": {
+                "anyOf": [
+                    { "type": "string" },
+                    { "type": "array", "items": { "type": "string" } },
+                ],
+            },
+            "block-comment-token": {
+                "type": "object",
+                "required": ["start", "end"],
+                "properties": {
+                    "start": { "type": "string" },
+                    "end": { "type": "string" },
+                },
+            },
+            "language-server": {
+                "type": "object",
+                "required": ["command"],
+                "properties": {
+                    "command": { "type": "string" },
+                    "args": { "type": "array", "items": { "type": "string" } },
+                    "environment": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                    },
+                    "config": {},
+                    "timeout": { "type": "integer", "minimum": 0 },
+                },
+            },
+            "repository": {
+                "anyOf": [
+                    {
+                        "type": "object",
+                        "required": ["path"],
+                        "properties": { "path": { "type": "string" } },
+                    },
+                    {
+                        "type": "object",
+                        "required": ["name", "remote", "branch"],
+                        "properties": {
+                            "name": { "type": "string" },
+                            "remote": { "type": "string" },
+                            "branch": { "type": "string" },
+                        },
+                    },
+                ],
+            },
+        },
+    })
+}
+
+pub fn languages_schema() -> Result<(), DynError> {
+    let schema = languages_json_schema();
+    let path = path::project_root().join("languages.schema.json");
+    std::fs::write(&path, format!("{:#}\n", schema))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::languages_json_schema;
+
+    #[test]
+    fn schema_covers_common_language_properties() {
+        let schema = languages_json_schema();
+        let language = &schema["definitions"]["language"]["properties"];
+        for property in ["grammar", "file-types", "language-servers"] {
+            assert!(
+                !language[property].is_null(),
+                "missing property: {property}"
+            );
+        }
+    }
+
+    #[test]
+    fn schema_covers_every_language_configuration_field() {
+        use helix_core::syntax::LanguageConfiguration;
+
+        // `deny_unknown_fields` makes serde list every accepted field in
+        // the error for an unknown one; harvesting that list keeps this
+        // hand-written schema from silently drifting when a field is
+        // added to `LanguageConfiguration`.
+        let err = toml::from_str::<LanguageConfiguration>("bogus-key = true").unwrap_err();
+        let message = err.to_string();
+        let (_, expected) = message
+            .split_once("expected one of ")
+            .expect("unknown-field errors list the accepted fields");
+
+        let schema = languages_json_schema();
+        let language = &schema["definitions"]["language"]["properties"];
+        // The list reads "`name`, `language-id`, ...": the odd-indexed
+        // pieces of a backtick split are the field names.
+        for field in expected.split('`').skip(1).step_by(2) {
+            assert!(
+                !language[field].is_null(),
+                "`{field}` is accepted by LanguageConfiguration but missing from the schema"
+            );
+        }
+    }
+}
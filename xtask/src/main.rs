@@ -1,26 +1,62 @@
+mod audit_grammars;
 mod docgen;
 mod helpers;
+mod languages_schema;
 mod path;
+mod span_goldens;
 mod theme_check;
+mod unused_queries;
 
 use std::{env, error::Error};
 
 type DynError = Box<dyn Error>;
 
 pub mod tasks {
-    use crate::docgen::{lang_features, typable_commands, write};
-    use crate::docgen::{LANG_SUPPORT_MD_OUTPUT, TYPABLE_COMMANDS_MD_OUTPUT};
-    use crate::theme_check::theme_check;
+    use crate::audit_grammars::audit_grammars;
+    use crate::docgen::{grammar_sources, lang_features, typable_commands, write};
+    use crate::docgen::{
+        GRAMMAR_SOURCES_MD_OUTPUT, LANG_SUPPORT_MD_OUTPUT, TYPABLE_COMMANDS_MD_OUTPUT,
+    };
+    use crate::languages_schema::languages_schema;
+    use crate::span_goldens::update_span_goldens;
+    use crate::theme_check::{theme_check, OutputFormat};
+    use crate::unused_queries::unused_queries;
     use crate::DynError;
 
     pub fn docgen() -> Result<(), DynError> {
         write(TYPABLE_COMMANDS_MD_OUTPUT, &typable_commands()?);
         write(LANG_SUPPORT_MD_OUTPUT, &lang_features()?);
+        write(GRAMMAR_SOURCES_MD_OUTPUT, &grammar_sources()?);
         Ok(())
     }
 
-    pub fn themecheck() -> Result<(), DynError> {
-        theme_check()
+    pub fn themecheck(args: &[String]) -> Result<(), DynError> {
+        let (format, dir) = match args {
+            [] => (OutputFormat::default(), None),
+            [dir] => (OutputFormat::default(), Some(dir)),
+            [flag, format] if flag == "--format" => (OutputFormat::from_arg(format)?, None),
+            [flag, format, dir] if flag == "--format" => {
+                (OutputFormat::from_arg(format)?, Some(dir))
+            }
+            _ => return Err(format!("Invalid theme-check arguments: {}", args.join(" ")).into()),
+        };
+        theme_check(format, dir.map(std::path::Path::new))
+    }
+
+    pub fn auditgrammars() -> Result<(), DynError> {
+        audit_grammars()
+    }
+
+    pub fn languagesschema() -> Result<(), DynError> {
+        languages_schema()
+    }
+
+    pub fn spangoldens() -> Result<(), DynError> {
+        update_span_goldens()
+    }
+
+    pub fn unusedqueries() -> Result<(), DynError> {
+        unused_queries()
     }
 
     pub fn print_help() {
@@ -36,12 +72,18 @@ Usage: Run with `cargo xtask <task>`, eg. `cargo xtask docgen`.
 }
 
 fn main() -> Result<(), DynError> {
-    let task = env::args().nth(1);
+    let mut args = env::args().skip(1);
+    let task = args.next();
+    let rest: Vec<String> = args.collect();
     match task {
         None => tasks::print_help(),
         Some(t) => match t.as_str() {
             "docgen" => tasks::docgen()?,
-            "theme-check" => tasks::themecheck()?,
+            "theme-check" => tasks::themecheck(&rest)?,
+            "audit-grammars" => tasks::auditgrammars()?,
+            "languages-schema" => tasks::languagesschema()?,
+            "update-span-goldens" => tasks::spangoldens()?,
+            "unused-queries" => tasks::unusedqueries()?,
             invalid => return Err(format!("Invalid task name: {}", invalid).into()),
         },
     };
@@ -0,0 +1,88 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use helix_core::syntax::{flat_span_iter, span_iter, HighlightEvent, Span};
+
+use crate::{path, DynError};
+
+/// The named span inputs covered by the golden files.
+///
+/// `flat` fixtures are rendered with `flat_span_iter` and must be
+/// non-overlapping; the rest go through `span_iter`. Adding a fixture here
+/// and running `cargo xtask update-span-goldens` creates its `.snap` file.
+fn fixtures() -> Vec<(&'static str, bool, Vec<Span>)> {
+    vec![
+        (
+            "many_overlapping",
+            false,
+            vec![
+                Span::new(0, 0, 10),
+                Span::new(1, 2, 8),
+                Span::new(2, 4, 12),
+                Span::new(3, 6, 7),
+            ],
+        ),
+        (
+            "disjoint",
+            true,
+            vec![Span::new(0, 0, 3), Span::new(1, 5, 5), Span::new(2, 6, 9)],
+        ),
+    ]
+}
+
+fn render(spans: Vec<Span>, flat: bool) -> String {
+    let events: Vec<HighlightEvent> = if flat {
+        flat_span_iter(spans).collect()
+    } else {
+        span_iter(spans).collect()
+    };
+
+    let mut rendered = String::new();
+    for event in events {
+        match event {
+            HighlightEvent::HighlightStart(highlight) => {
+                writeln!(rendered, "START {}", highlight.0).unwrap()
+            }
+            HighlightEvent::Source { start, end } => {
+                writeln!(rendered, "SOURCE {start}..{end}").unwrap()
+            }
+            HighlightEvent::HighlightEnd => writeln!(rendered, "END").unwrap(),
+        }
+    }
+    rendered
+}
+
+fn goldens_dir() -> PathBuf {
+    path::project_root().join("xtask").join("goldens")
+}
+
+pub fn update_span_goldens() -> Result<(), DynError> {
+    let dir = goldens_dir();
+    std::fs::create_dir_all(&dir)?;
+    for (name, flat, spans) in fixtures() {
+        let path = dir.join(format!("{name}.snap"));
+        std::fs::write(&path, render(spans, flat))?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixtures, goldens_dir, render};
+
+    #[test]
+    fn span_goldens_are_current() {
+        for (name, flat, spans) in fixtures() {
+            let path = goldens_dir().join(format!("{name}.snap"));
+            let golden = std::fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+            assert_eq!(
+                render(spans, flat),
+                golden,
+                "golden '{name}' is stale; run `cargo xtask update-span-goldens` \
+                 and review the diff"
+            );
+        }
+    }
+}
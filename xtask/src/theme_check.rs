@@ -1,33 +1,199 @@
+use std::path::Path;
+
 use helix_view::theme::Loader;
 
 use crate::{path, DynError};
 
-pub fn theme_check() -> Result<(), DynError> {
-    let theme_names = [
-        vec!["default".to_string(), "base16_default".to_string()],
-        Loader::read_names(&path::themes()),
-    ]
-    .concat();
-    let loader = Loader::new(&[path::runtime()]);
-    let mut errors_present = false;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn from_arg(arg: &str) -> Result<Self, DynError> {
+        match arg {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            invalid => Err(format!("Invalid output format: {}", invalid).into()),
+        }
+    }
+}
+
+/// One issue found while loading a theme.
+///
+/// `severity` is `"warning"` for a theme that loads with issues and
+/// `"error"` for a theme that fails to load entirely (e.g. invalid
+/// TOML); `scope` names the offending theme key when attributable.
+struct Issue {
+    theme: String,
+    scope: Option<String>,
+    severity: &'static str,
+    message: String,
+}
+
+impl Issue {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "theme": self.theme,
+            "scope": self.scope,
+            "severity": self.severity,
+            "message": self.message,
+        })
+    }
+}
+
+fn check_theme(loader: &Loader, name: &str) -> Vec<Issue> {
+    match loader.load_with_warnings(name) {
+        Ok((_, warnings)) => warnings
+            .into_iter()
+            .map(|warning| Issue {
+                theme: name.to_string(),
+                scope: warning.scope,
+                severity: "warning",
+                message: warning.message,
+            })
+            .collect(),
+        Err(err) => vec![Issue {
+            theme: name.to_string(),
+            scope: None,
+            severity: "error",
+            message: err.to_string(),
+        }],
+    }
+}
 
+pub fn theme_check(format: OutputFormat, dir: Option<&Path>) -> Result<(), DynError> {
+    // With an explicit directory only the themes found there are checked;
+    // the runtime themes stay available as inheritance parents. By default
+    // the bundled themes are checked.
+    let (theme_names, loader) = match dir {
+        Some(dir) => {
+            if !dir.is_dir() {
+                return Err(format!("'{}' is not a theme directory", dir.display()).into());
+            }
+            (
+                Loader::read_names(dir),
+                Loader::with_theme_dirs(vec![dir.to_path_buf(), path::themes()]),
+            )
+        }
+        None => (
+            [
+                vec!["default".to_string(), "base16_default".to_string()],
+                Loader::read_names(&path::themes()),
+            ]
+            .concat(),
+            Loader::new(&[path::runtime()]),
+        ),
+    };
+
+    let mut issues = Vec::new();
     for name in theme_names {
-        let (_, warnings) = loader.load_with_warnings(&name).unwrap();
+        let theme_issues = check_theme(&loader, &name);
 
-        if !warnings.is_empty() {
-            errors_present = true;
+        if format == OutputFormat::Text && !theme_issues.is_empty() {
             println!("Theme '{name}' loaded with errors:");
-            for warning in warnings {
-                println!("\t* {}", warning);
+            for issue in &theme_issues {
+                match &issue.scope {
+                    Some(scope) => println!("\t* {}: {}", scope, issue.message),
+                    None => println!("\t* {}", issue.message),
+                }
             }
         }
+
+        issues.extend(theme_issues);
     }
 
-    match errors_present {
-        true => Err("Errors found when loading bundled themes".into()),
-        false => {
-            println!("Theme check successful!");
+    if format == OutputFormat::Json {
+        let objects: Vec<serde_json::Value> = issues.iter().map(Issue::to_json).collect();
+        println!("{}", serde_json::to_string_pretty(&objects)?);
+    }
+
+    match issues.is_empty() {
+        false => Err("Errors found when loading themes".into()),
+        true => {
+            if format == OutputFormat::Text {
+                println!("Theme check successful!");
+            }
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use helix_view::theme::Loader;
+
+    use super::{check_theme, theme_check, OutputFormat};
+    use crate::path;
+
+    fn fixture_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("helix-theme-check-{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn checks_themes_in_custom_directory() {
+        let dir = fixture_dir("custom");
+        std::fs::write(
+            dir.join("good.toml"),
+            "\"ui.background\" = { bg = \"#000000\" }\n",
+        )
+        .unwrap();
+        assert!(theme_check(OutputFormat::Text, Some(&dir)).is_ok());
+
+        std::fs::write(dir.join("broken.toml"), "this is not a theme {{{\n").unwrap();
+        assert!(theme_check(OutputFormat::Text, Some(&dir)).is_err());
+    }
+
+    #[test]
+    fn invalid_directory_is_an_error() {
+        let err = theme_check(
+            OutputFormat::Text,
+            Some(Path::new("/nonexistent/helix-themes")),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not a theme directory"));
+    }
+
+    #[test]
+    fn json_shape_reports_scope_and_severity() {
+        let dir = fixture_dir("json");
+        // A theme that loads, but with one bad style...
+        std::fs::write(
+            dir.join("warny.toml"),
+            "\"keyword\" = { fg = \"#zzzzzz\" }\n",
+        )
+        .unwrap();
+        // ...and one that fails to load entirely.
+        std::fs::write(dir.join("broken.toml"), "this is not a theme {{{\n").unwrap();
+        let loader = Loader::with_theme_dirs(vec![dir, path::themes()]);
+
+        let issues = check_theme(&loader, "warny");
+        assert_eq!(issues.len(), 1);
+        let json = issues[0].to_json();
+        assert_eq!(json["theme"], "warny");
+        assert_eq!(json["scope"], "keyword");
+        assert_eq!(json["severity"], "warning");
+        assert!(
+            json["message"]
+                .as_str()
+                .unwrap()
+                .contains("Malformed hexcode"),
+            "unexpected message: {}",
+            json["message"]
+        );
+
+        let issues = check_theme(&loader, "broken");
+        assert_eq!(issues.len(), 1);
+        let json = issues[0].to_json();
+        assert_eq!(json["theme"], "broken");
+        assert!(json["scope"].is_null());
+        assert_eq!(json["severity"], "error");
+    }
+}
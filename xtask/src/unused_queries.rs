@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use crate::DynError;
+
+/// The query directories under `dir` named after no configured language —
+/// leftovers from removed or renamed languages.
+///
+/// `languages` is the list of language ids from the merged config.
+/// Non-directory entries are skipped; a missing `dir` reports nothing.
+fn orphaned_query_dirs(dir: &Path, languages: &[&str]) -> std::io::Result<Vec<PathBuf>> {
+    let mut orphaned = Vec::new();
+    if !dir.is_dir() {
+        return Ok(orphaned);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name();
+        if !languages
+            .iter()
+            .any(|language| std::ffi::OsStr::new(language) == name)
+        {
+            orphaned.push(entry.path());
+        }
+    }
+    orphaned.sort();
+    Ok(orphaned)
+}
+
+pub fn unused_queries() -> Result<(), DynError> {
+    let config = crate::helpers::lang_config();
+    let languages: Vec<&str> = config.language_ids().collect();
+
+    let mut orphaned = orphaned_query_dirs(&crate::path::runtime().join("queries"), &languages)?;
+    // Grammar source checkouts carry per-grammar query directories too.
+    let sources = crate::path::runtime().join("grammars/sources");
+    if sources.is_dir() {
+        for entry in std::fs::read_dir(&sources)? {
+            let path = entry?.path();
+            let queries = path.join("queries");
+            if !queries.is_dir() {
+                continue;
+            }
+            let orphan = path.file_name().map_or(false, |name| {
+                !languages
+                    .iter()
+                    .any(|language| std::ffi::OsStr::new(language) == name)
+            });
+            if orphan {
+                orphaned.push(queries);
+            }
+        }
+        orphaned.sort();
+    }
+
+    if orphaned.is_empty() {
+        println!("All runtime query directories match a configured language");
+        return Ok(());
+    }
+
+    for dir in &orphaned {
+        println!(
+            "Query directory {} has no matching language in languages.toml",
+            dir.display()
+        );
+    }
+    Err(format!(
+        "{} query directories belong to no configured language",
+        orphaned.len()
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::orphaned_query_dirs;
+
+    #[test]
+    fn orphaned_query_dir_is_reported() {
+        let fixture =
+            std::env::temp_dir().join(format!("helix-xtask-unused-queries-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&fixture);
+        std::fs::create_dir_all(fixture.join("rust")).unwrap();
+        std::fs::create_dir_all(fixture.join("removed-lang")).unwrap();
+        // Stray files are not query directories and must be ignored.
+        std::fs::write(fixture.join("README.md"), "").unwrap();
+
+        let orphaned = orphaned_query_dirs(&fixture, &["rust", "toml"]).unwrap();
+        assert_eq!(orphaned, vec![fixture.join("removed-lang")]);
+
+        // A missing directory reports nothing rather than erroring.
+        assert!(orphaned_query_dirs(&fixture.join("missing"), &["rust"])
+            .unwrap()
+            .is_empty());
+
+        std::fs::remove_dir_all(&fixture).unwrap();
+    }
+}